@@ -0,0 +1,12 @@
+#!/bin/bash
+set -eu
+
+CW20_VERSION=v1.1.2
+RELEASE=https://github.com/CosmWasm/cw-plus/releases/download/$CW20_VERSION
+
+echo "Fetching cw20-base $CW20_VERSION for tests...."
+mkdir -p artifacts
+curl -sSfL -o artifacts/cw20_base.wasm $RELEASE/cw20_base.wasm
+curl -sSfL -o artifacts/checksums.txt $RELEASE/checksums.txt
+
+cd artifacts && grep " cw20_base.wasm$" checksums.txt | sha256sum -c -
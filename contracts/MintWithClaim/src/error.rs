@@ -1,13 +1,25 @@
+use cosmwasm_std::{Coin, StdError, Timestamp, Uint128};
+use cw20::Cw20CoinVerified;
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
+    #[error(transparent)]
+    Std(#[from] StdError),
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
     #[error("Signature already used!")]
     Claimed {},
 
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Cannot migrate from {contract} {version}")]
+    CannotMigrate { contract: String, version: String },
+
     #[error("Not receiver!")]
     NotReceiver {},
 
@@ -20,4 +32,130 @@ pub enum ContractError {
         is_sign_valid: bool,
         has_role: bool,
     },
+
+    #[error("Claim expired at {expires_at}")]
+    ClaimExpired { expires_at: Timestamp },
+
+    #[error("Claim not valid before {issued_at}")]
+    ClaimNotYetValid { issued_at: Timestamp },
+
+    #[error("Invalid claim validity window")]
+    InvalidClaimWindow {},
+
+    #[error("Claim validity exceeds maximum of {max_seconds} seconds")]
+    ClaimValidityTooLong { max_seconds: u64 },
+
+    #[error("Insufficient fee, required {required} but paid {paid}")]
+    InsufficientFee { required: Coin, paid: Uint128 },
+
+    #[error("Claim signed for chain {expected}, not {actual}")]
+    WrongChainId { expected: String, actual: String },
+
+    #[error("Claim signed for contract {expected}, not {actual}")]
+    WrongClaimContract { expected: String, actual: String },
+
+    #[error("Invalid issuer key")]
+    InvalidIssuerKey {},
+
+    #[error("Malleable signature, s must be in the lower half order")]
+    MalleableSignature {},
+
+    #[error("Issuer key not registered")]
+    IssuerKeyNotRegistered {},
+
+    #[error("Issuer key revoked")]
+    IssuerKeyRevoked {},
+
+    #[error("Issuer key not active")]
+    IssuerKeyInactive {},
+
+    #[error("Issuer key not allowed to sign for {contract}")]
+    IssuerKeyOutOfScope { contract: String },
+
+    #[error("Batch must contain between 1 and {max} claims")]
+    InvalidBatchSize { max: usize },
+
+    #[error("Invalid recipient consent")]
+    InvalidRecipientConsent {},
+
+    #[error("No Merkle root set for campaign {campaign}")]
+    MerkleRootNotFound { campaign: String },
+
+    #[error("Invalid Merkle root")]
+    InvalidMerkleRoot {},
+
+    #[error("Invalid Merkle proof")]
+    InvalidMerkleProof {},
+
+    #[error("Allowlist leaf already claimed in campaign {campaign}")]
+    LeafClaimed { campaign: String },
+
+    #[error("Claim extension must be a JSON object: {msg}")]
+    InvalidExtension { msg: String },
+
+    #[error("Campaign {id} not found")]
+    CampaignNotFound { id: String },
+
+    #[error("Campaign {id} already exists")]
+    CampaignExists { id: String },
+
+    #[error("Campaign {id} is closed")]
+    CampaignClosed { id: String },
+
+    #[error("Campaign {id} is not active")]
+    CampaignNotActive { id: String },
+
+    #[error("Campaign {id} reached its maximum claims")]
+    CampaignSupplyReached { id: String },
+
+    #[error("Address reached the claim limit of campaign {id}")]
+    CampaignAddressLimitReached { id: String },
+
+    #[error("Claim targets a different contract than campaign {id}")]
+    CampaignContractMismatch { id: String },
+
+    #[error("Campaign requires a fee of {required}")]
+    CampaignFeeMismatch { required: Coin },
+
+    #[error("Invalid campaign config")]
+    InvalidCampaignConfig {},
+
+    #[error("Membership contract {contract} is not registered")]
+    MembershipContractNotRegistered { contract: String },
+
+    #[error("Membership contract {contract} is disabled")]
+    MembershipContractDisabled { contract: String },
+
+    #[error("Invalid co-signature from {signer}")]
+    InvalidCoSignature { signer: String },
+
+    #[error("Duplicate claim signer {signer}")]
+    DuplicateSigner { signer: String },
+
+    #[error("Claim needs {required} distinct issuer signatures, got {signers}")]
+    IssuerThresholdNotMet { required: u32, signers: u32 },
+
+    #[error("Claim revoked")]
+    ClaimRevoked {},
+
+    #[error("Revocation must contain between 1 and {max} claims")]
+    InvalidRevocationSize { max: usize },
+
+    #[error("Claim fee must be paid with the signed cw20 token and amount")]
+    Cw20FeeMismatch {},
+
+    #[error("Fee split needs 1 to {max} distinct recipients with weights adding up to 10000 bps")]
+    InvalidFeeSplit { max: usize },
+
+    #[error("Claim recipient cannot be its own referrer")]
+    SelfReferral {},
+
+    #[error("Referral share cannot exceed 10000 bps")]
+    InvalidReferralShare {},
+
+    #[error("Withdrawal must be between 1 and the vault balance of {available}")]
+    InsufficientVaultBalance { available: Coin },
+
+    #[error("Withdrawal must be between 1 and the cw20 vault balance of {available}")]
+    InsufficientCw20VaultBalance { available: Cw20CoinVerified },
 }
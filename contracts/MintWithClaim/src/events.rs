@@ -0,0 +1,226 @@
+//! Events emitted by MintWithClaim. The chain prefixes custom event types with `wasm-`, so
+//! indexers see `wasm-mercle_claim`, `wasm-mercle_claim_refund`, `wasm-mercle_role`,
+//! `wasm-mercle_treasury`, `wasm-mercle_fee_split`, `wasm-mercle_vault`, `wasm-mercle_withdraw`,
+//! `wasm-mercle_issuer_key`, `wasm-mercle_config` and `wasm-mercle_merkle_root`.
+//!
+//! | Event                 | Attributes                                                       |
+//! |-----------------------|------------------------------------------------------------------|
+//! | `mercle_claim`        | `claim_id`, `source`, `signer`\*, `signers`\*, `recipient`, `contract`, `token_uri`, `fee`, `cw20_fee`\*, `campaign_id`\*, `referrer`\*, `extension`\*, `actor` |
+//! | `mercle_claim_refund` | `claim_id`, `reason`                                             |
+//! | `mercle_role`         | `action`, `role`, `address`, `actor`                             |
+//! | `mercle_treasury`     | `treasury`, `actor`                                              |
+//! | `mercle_fee_split`    | `recipients`\*, `actor`                                          |
+//! | `mercle_vault`        | `enabled`, `actor`                                               |
+//! | `mercle_withdraw`     | `recipient`, `denom`\*, `token`\*, `amount`, `actor`             |
+//! | `mercle_issuer_key`   | `action`, `key`, `key_type`, `issuer`, `new_key`\*, `actor`      |
+//! | `mercle_config`       | `setting`, `value`, `actor`                                      |
+//! | `mercle_merkle_root`  | `contract`, `campaign`, `root`\*, `actor`                        |
+//!
+//! \* only present when set. `signers` is a comma separated list of every issuer that signed,
+//! `fee` is the charged coin, e.g. `1000uxion`, and `0` when nothing was charged. `cw20_fee` is
+//! the amount followed by the token contract, e.g. `1000xion1...`. `extension` is the JSON
+//! forwarded to the membership `Mint`. `recipients` lists the global
+//! fee split as `address:bps` pairs and is left out once the split is removed. `amount` of a
+//! withdrawal is the bare integer amount of the native `denom` or the cw20 `token` withdrawn. Issuer keys and Merkle roots are base64, the
+//! issuer key `action` is one of `register`, `revoke`, `rotate` or `remove`, and `new_key` is
+//! only set on rotation. `mercle_config` reports `digest_version`, `max_claim_validity` or
+//! `mint_failure_mode` as `setting`, with `none` as `value` when the setting is cleared.
+//! wasmd redacts the errors of failed submessages, so the `reason` of a refund is only the error
+//! code, e.g. `codespace: wasm, code: 5`, not the membership contract's message.
+
+use crate::state::{ClaimRecord, FeeShare, IssuerKey, Role};
+use cosmwasm_std::{Addr, Binary, Coin, Event};
+use cw20::Cw20CoinVerified;
+
+pub const CLAIM_EVENT: &str = "mercle_claim";
+pub const CLAIM_REFUND_EVENT: &str = "mercle_claim_refund";
+pub const ROLE_EVENT: &str = "mercle_role";
+pub const TREASURY_EVENT: &str = "mercle_treasury";
+pub const FEE_SPLIT_EVENT: &str = "mercle_fee_split";
+pub const VAULT_EVENT: &str = "mercle_vault";
+pub const WITHDRAW_EVENT: &str = "mercle_withdraw";
+pub const ISSUER_KEY_EVENT: &str = "mercle_issuer_key";
+pub const CONFIG_EVENT: &str = "mercle_config";
+pub const MERKLE_ROOT_EVENT: &str = "mercle_merkle_root";
+
+pub const ATTR_CLAIM_ID: &str = "claim_id";
+pub const ATTR_SOURCE: &str = "source";
+pub const ATTR_SIGNER: &str = "signer";
+pub const ATTR_SIGNERS: &str = "signers";
+pub const ATTR_RECIPIENT: &str = "recipient";
+pub const ATTR_CONTRACT: &str = "contract";
+pub const ATTR_TOKEN_URI: &str = "token_uri";
+pub const ATTR_FEE: &str = "fee";
+pub const ATTR_CW20_FEE: &str = "cw20_fee";
+pub const ATTR_CAMPAIGN_ID: &str = "campaign_id";
+pub const ATTR_REFERRER: &str = "referrer";
+pub const ATTR_EXTENSION: &str = "extension";
+pub const ATTR_REASON: &str = "reason";
+pub const ATTR_ACTION: &str = "action";
+pub const ATTR_ROLE: &str = "role";
+pub const ATTR_ADDRESS: &str = "address";
+pub const ATTR_TREASURY: &str = "treasury";
+pub const ATTR_RECIPIENTS: &str = "recipients";
+pub const ATTR_ENABLED: &str = "enabled";
+pub const ATTR_DENOM: &str = "denom";
+pub const ATTR_TOKEN: &str = "token";
+pub const ATTR_AMOUNT: &str = "amount";
+pub const ATTR_KEY: &str = "key";
+pub const ATTR_KEY_TYPE: &str = "key_type";
+pub const ATTR_ISSUER: &str = "issuer";
+pub const ATTR_NEW_KEY: &str = "new_key";
+pub const ATTR_SETTING: &str = "setting";
+pub const ATTR_VALUE: &str = "value";
+pub const ATTR_CAMPAIGN: &str = "campaign";
+pub const ATTR_ROOT: &str = "root";
+pub const ATTR_ACTOR: &str = "actor";
+
+/// `source` of claims redeemed with issuer signatures.
+pub const SOURCE_SIGNATURE: &str = "signature";
+/// `source` of claims redeemed with a Merkle allowlist proof.
+pub const SOURCE_MERKLE: &str = "merkle";
+
+pub fn claim_event(
+    claim_id: u64,
+    record: &ClaimRecord,
+    signers: &[Addr],
+    extension: Option<&Binary>,
+    actor: &Addr,
+) -> Event {
+    let source = match record.issuer {
+        Some(_) => SOURCE_SIGNATURE,
+        None => SOURCE_MERKLE,
+    };
+    let fee = match record.fee.amount.is_zero() {
+        true => "0".to_string(),
+        false => record.fee.to_string(),
+    };
+
+    let mut event = Event::new(CLAIM_EVENT)
+        .add_attribute(ATTR_CLAIM_ID, claim_id.to_string())
+        .add_attribute(ATTR_SOURCE, source);
+    if let Some(issuer) = &record.issuer {
+        event = event.add_attribute(ATTR_SIGNER, issuer);
+    }
+    if !signers.is_empty() {
+        let signers: Vec<&str> = signers.iter().map(Addr::as_str).collect();
+        event = event.add_attribute(ATTR_SIGNERS, signers.join(","));
+    }
+    event = event
+        .add_attribute(ATTR_RECIPIENT, &record.recipient)
+        .add_attribute(ATTR_CONTRACT, &record.verifying_contract)
+        .add_attribute(ATTR_TOKEN_URI, &record.token_uri)
+        .add_attribute(ATTR_FEE, fee);
+    if let Some(cw20_fee) = &record.cw20_fee {
+        event = event.add_attribute(
+            ATTR_CW20_FEE,
+            format!("{}{}", cw20_fee.amount, cw20_fee.address),
+        );
+    }
+    if let Some(campaign_id) = &record.campaign_id {
+        event = event.add_attribute(ATTR_CAMPAIGN_ID, campaign_id);
+    }
+    if let Some(referrer) = &record.referrer {
+        event = event.add_attribute(ATTR_REFERRER, referrer);
+    }
+    if let Some(extension) = extension {
+        event = event.add_attribute(ATTR_EXTENSION, String::from_utf8_lossy(extension));
+    }
+    event.add_attribute(ATTR_ACTOR, actor)
+}
+
+/// Emitted when a claim's mint failed and its fee was refunded, `claim_id` refers to the
+/// preceding `mercle_claim` event and is no longer recorded.
+pub fn claim_refund_event(claim_id: u64, reason: &str) -> Event {
+    Event::new(CLAIM_REFUND_EVENT)
+        .add_attribute(ATTR_CLAIM_ID, claim_id.to_string())
+        .add_attribute(ATTR_REASON, reason)
+}
+
+pub fn role_event(action: &str, role: Role, address: &Addr, actor: &Addr) -> Event {
+    Event::new(ROLE_EVENT)
+        .add_attribute(ATTR_ACTION, action)
+        .add_attribute(ATTR_ROLE, format!("{:?}", role))
+        .add_attribute(ATTR_ADDRESS, address)
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn treasury_event(treasury: &Addr, actor: &Addr) -> Event {
+    Event::new(TREASURY_EVENT)
+        .add_attribute(ATTR_TREASURY, treasury)
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn fee_split_event(split: &[FeeShare], actor: &Addr) -> Event {
+    let recipients: Vec<String> = split
+        .iter()
+        .map(|share| format!("{}:{}", share.address, share.bps))
+        .collect();
+    let mut event = Event::new(FEE_SPLIT_EVENT);
+    if !recipients.is_empty() {
+        event = event.add_attribute(ATTR_RECIPIENTS, recipients.join(","));
+    }
+    event.add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn vault_event(enabled: bool, actor: &Addr) -> Event {
+    Event::new(VAULT_EVENT)
+        .add_attribute(ATTR_ENABLED, enabled.to_string())
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn withdraw_event(recipient: &Addr, amount: &Coin, actor: &Addr) -> Event {
+    Event::new(WITHDRAW_EVENT)
+        .add_attribute(ATTR_RECIPIENT, recipient)
+        .add_attribute(ATTR_DENOM, &amount.denom)
+        .add_attribute(ATTR_AMOUNT, amount.amount.to_string())
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn cw20_withdraw_event(recipient: &Addr, amount: &Cw20CoinVerified, actor: &Addr) -> Event {
+    Event::new(WITHDRAW_EVENT)
+        .add_attribute(ATTR_RECIPIENT, recipient)
+        .add_attribute(ATTR_TOKEN, &amount.address)
+        .add_attribute(ATTR_AMOUNT, amount.amount.to_string())
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn issuer_key_event(
+    action: &str,
+    key: &Binary,
+    issuer_key: &IssuerKey,
+    new_key: Option<&Binary>,
+    actor: &Addr,
+) -> Event {
+    let mut event = Event::new(ISSUER_KEY_EVENT)
+        .add_attribute(ATTR_ACTION, action)
+        .add_attribute(ATTR_KEY, key.to_base64())
+        .add_attribute(ATTR_KEY_TYPE, format!("{:?}", issuer_key.key_type))
+        .add_attribute(ATTR_ISSUER, &issuer_key.issuer);
+    if let Some(new_key) = new_key {
+        event = event.add_attribute(ATTR_NEW_KEY, new_key.to_base64());
+    }
+    event.add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn config_event(setting: &str, value: Option<String>, actor: &Addr) -> Event {
+    Event::new(CONFIG_EVENT)
+        .add_attribute(ATTR_SETTING, setting)
+        .add_attribute(ATTR_VALUE, value.unwrap_or_else(|| "none".to_string()))
+        .add_attribute(ATTR_ACTOR, actor)
+}
+
+pub fn merkle_root_event(
+    verifying_contract: &Addr,
+    campaign: &str,
+    root: Option<&Binary>,
+    actor: &Addr,
+) -> Event {
+    let mut event = Event::new(MERKLE_ROOT_EVENT)
+        .add_attribute(ATTR_CONTRACT, verifying_contract)
+        .add_attribute(ATTR_CAMPAIGN, campaign);
+    if let Some(root) = root {
+        event = event.add_attribute(ATTR_ROOT, root.to_base64());
+    }
+    event.add_attribute(ATTR_ACTOR, actor)
+}
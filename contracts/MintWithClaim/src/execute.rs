@@ -1,10 +1,29 @@
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MemberhsipExecute, MembershipMintMsg, Message};
-use crate::state::{MintWithClaimContract, Role};
+use crate::events::{
+    claim_event, claim_refund_event, config_event, cw20_withdraw_event, fee_split_event,
+    issuer_key_event, merkle_root_event, role_event, treasury_event, vault_event, withdraw_event,
+};
+use crate::helpers::{
+    check_fee_payment, check_fees_payment, membership_mint_msg, merkle_leaf, message_digest,
+    parse_extension, refund_coins, split_amount, sum_fees, verify_merkle_proof,
+};
+use crate::msg::{
+    BatchMode, Claim, ClaimRevocation, Cw20HookMsg, ExecuteMsg, InstantiateMsg, Message,
+};
+use crate::state::{
+    Campaign, CampaignConfig, ClaimRecord, DigestVersion, FeeShare, IssuerKey, KeyType,
+    MembershipContract, MintFailureMode, MintSource, MintWithClaimContract, PendingMint, Role,
+    FULL_BPS,
+};
 use cosmwasm_std::{
-    to_json_binary, Addr, BankMsg, Binary, CosmosMsg, CustomMsg, DepsMut, Empty, Env, MessageInfo,
-    Response, StdResult, WasmMsg,
+    from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, CustomMsg, Deps, DepsMut,
+    Env, MessageInfo, Reply, Response, StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
 };
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg};
+
+const MAX_BATCH_SIZE: usize = 50;
+const MAX_REVOCATIONS: usize = 100;
+const MAX_FEE_RECIPIENTS: usize = 10;
 
 impl<'a, C> MintWithClaimContract<'a, C>
 where
@@ -18,26 +37,150 @@ where
         msg: InstantiateMsg,
     ) -> StdResult<Response<C>> {
         self.treasury.save(deps.storage, &msg.treasury)?;
+        if let Some(seconds) = msg.max_claim_validity {
+            self.max_claim_validity.save(deps.storage, &seconds)?;
+        }
+        self.digest_version.save(
+            deps.storage,
+            &msg.digest_version.unwrap_or(DigestVersion::V1),
+        )?;
         self.update_role(deps.storage, &_info.sender, Role::DefaultAdmin, true)?;
-        Ok(Response::default())
+        Ok(Response::new()
+            .add_event(treasury_event(&msg.treasury, &_info.sender))
+            .add_event(role_event(
+                "grant",
+                Role::DefaultAdmin,
+                &_info.sender,
+                &_info.sender,
+            )))
     }
 
     pub fn execute(
         &self,
         deps: DepsMut,
-        _env: Env,
+        env: Env,
         info: MessageInfo,
         msg: ExecuteMsg,
     ) -> Result<Response<C>, ContractError> {
         match msg {
             ExecuteMsg::SetTreasury { address } => self.set_treasury(deps, info, address),
+            ExecuteMsg::SetFeeSplit { split } => self.set_fee_split(deps, info, split),
+            ExecuteMsg::SetReferralShare { bps } => self.set_referral_share(deps, info, bps),
+            ExecuteMsg::SetVaultMode { enabled } => self.set_vault_mode(deps, info, enabled),
+            ExecuteMsg::Withdraw {
+                denom,
+                amount,
+                recipient,
+            } => self.withdraw(deps, info, denom, amount, recipient),
+            ExecuteMsg::WithdrawCw20 {
+                token,
+                amount,
+                recipient,
+            } => self.withdraw_cw20(deps, info, token, amount, recipient),
+            ExecuteMsg::SetMaxClaimValidity { seconds } => {
+                self.set_max_claim_validity(deps, info, seconds)
+            }
+            ExecuteMsg::SetDigestVersion { version } => {
+                self.set_digest_version(deps, info, version)
+            }
+            ExecuteMsg::SetMintFailureMode { mode } => self.set_mint_failure_mode(deps, info, mode),
             ExecuteMsg::GrantRole { role, address } => self.grant_role(deps, info, address, role),
             ExecuteMsg::RevokeRole { role, address } => self.revoke_role(deps, info, address, role),
+            ExecuteMsg::RegisterIssuerKey {
+                key_type,
+                key,
+                issuer,
+                label,
+                scope,
+                valid_from,
+                valid_until,
+            } => self.register_issuer_key(
+                deps,
+                info,
+                key,
+                IssuerKey {
+                    key_type,
+                    issuer,
+                    label,
+                    scope,
+                    valid_from,
+                    valid_until,
+                    revoked: false,
+                },
+            ),
+            ExecuteMsg::RevokeIssuerKey { key } => self.revoke_issuer_key(deps, info, key),
+            ExecuteMsg::RotateIssuerKey {
+                key,
+                new_key_type,
+                new_key,
+            } => self.rotate_issuer_key(deps, env, info, key, new_key_type, new_key),
+            ExecuteMsg::RemoveIssuerKey { key } => self.remove_issuer_key(deps, info, key),
             ExecuteMsg::MintWithClaim {
                 message,
                 signature,
                 recovery_byte,
-            } => self.mint_with_claim(deps, info, message, signature, recovery_byte),
+                sign_mode,
+                consent,
+                co_signatures,
+            } => self.mint_with_claim(
+                deps,
+                env,
+                info,
+                Claim {
+                    message,
+                    signature,
+                    recovery_byte,
+                    sign_mode,
+                    consent,
+                    co_signatures,
+                },
+            ),
+            ExecuteMsg::Receive(wrapper) => self.receive_cw20(deps, env, info, wrapper),
+            ExecuteMsg::SetMerkleRoot {
+                verifying_contract,
+                campaign,
+                root,
+            } => self.set_merkle_root(deps, info, verifying_contract, campaign, root),
+            ExecuteMsg::MintWithMerkleProof {
+                verifying_contract,
+                campaign,
+                token_uri,
+                fee,
+                proof,
+            } => self.mint_with_merkle_proof(
+                deps,
+                env,
+                info,
+                verifying_contract,
+                campaign,
+                token_uri,
+                fee,
+                proof,
+            ),
+            ExecuteMsg::CreateCampaign { id, config } => {
+                self.create_campaign(deps, info, id, config)
+            }
+            ExecuteMsg::UpdateCampaign { id, config } => {
+                self.update_campaign(deps, info, id, config)
+            }
+            ExecuteMsg::CloseCampaign { id } => self.close_campaign(deps, info, id),
+            ExecuteMsg::RegisterMembershipContract { contract, config } => {
+                self.register_membership_contract(deps, info, contract, config)
+            }
+            ExecuteMsg::RemoveMembershipContract { contract } => {
+                self.remove_membership_contract(deps, info, contract)
+            }
+            ExecuteMsg::RevokeClaim { revocation } => {
+                self.revoke_claims(deps, info, vec![revocation])
+            }
+            ExecuteMsg::RevokeClaims { revocations } => self.revoke_claims(deps, info, revocations),
+            ExecuteMsg::MintWithClaimBatch { claims, mode } => self.mint_with_claim_batch(
+                deps,
+                env,
+                info,
+                claims,
+                mode.unwrap_or(BatchMode::AllOrNothing),
+            ),
         }
     }
 }
@@ -46,62 +189,613 @@ where
     C: CustomMsg,
 {
     fn mint_with_claim(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        claim: Claim,
+    ) -> Result<Response<C>, ContractError> {
+        let (message, signers) = self.accept_claim(deps.branch(), &env, &info, claim, None)?;
+        let (split, fee, refund) = self.check_claim_fee(deps.storage, &info, &message)?;
+
+        let refunds = refund_coins(&fee.denom, refund);
+
+        let record = claim_record(&env, &message, &fee);
+        let claim_id = self.record_claim(deps.storage, &record)?;
+
+        let response = Response::new()
+            .add_event(claim_event(
+                claim_id,
+                &record,
+                &signers,
+                message.extension.as_ref(),
+                &info.sender,
+            ))
+            .add_submessage(self.dispatch_claim_mint(
+                deps.storage,
+                claim_id,
+                &info.sender,
+                message,
+                split,
+                fee,
+            )?);
+
+        Ok(self.route_fees(response, &info.sender, vec![], refunds))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn mint_with_merkle_proof(
         &self,
         deps: DepsMut,
-        _info: MessageInfo,
-        message: Message,
-        signature: Binary,
-        recovery_byte: u8,
-    ) -> Result<Response<C>, ContractError> {
-        let (is_duplicate, is_sign_valid, has_role) = self
-            .validate_claim(
-                deps.as_ref(),
-                message.to_owned(),
-                signature.to_owned(),
-                recovery_byte,
-            )
-            .map_err(|e| ContractError::ValidationError { msg: e.to_string() })?;
+        env: Env,
+        info: MessageInfo,
+        verifying_contract: Addr,
+        campaign: String,
+        token_uri: String,
+        fee: Coin,
+        proof: Vec<Binary>,
+    ) -> Result<Response<C>, ContractError> {
+        self.check_membership_contract(deps.storage, &verifying_contract)?;
+
+        let root = self
+            .merkle_roots
+            .may_load(deps.storage, (&verifying_contract, &campaign))?
+            .ok_or_else(|| ContractError::MerkleRootNotFound {
+                campaign: campaign.to_owned(),
+            })?;
+
+        let leaf = merkle_leaf(&info.sender, &token_uri, &fee);
+        if !verify_merkle_proof(&root, leaf, &proof) {
+            return Err(ContractError::InvalidMerkleProof {});
+        }
 
-        let is_valid = !is_duplicate && is_sign_valid && has_role;
+        let leaf_key = (&verifying_contract, campaign.as_str(), leaf.as_slice());
+        if self
+            .used_leaves
+            .may_load(deps.storage, leaf_key)?
+            .unwrap_or_default()
+        {
+            return Err(ContractError::LeafClaimed { campaign });
+        }
+        self.used_leaves.save(deps.storage, leaf_key, &true)?;
 
-        if !is_valid {
-            return Err(ContractError::VerificationFailure {
-                has_role,
-                is_duplicate,
-                is_sign_valid,
+        // An allowlist named after a campaign counts against that campaign's limits
+        let campaign_id = match self.campaigns.has(deps.storage, &campaign) {
+            true => {
+                self.check_campaign_claim(
+                    deps.storage,
+                    &env,
+                    &campaign,
+                    &verifying_contract,
+                    &info.sender,
+                    &fee,
+                )?;
+                self.count_campaign_claim(deps.storage, &campaign, &info.sender)?;
+                Some(campaign.to_owned())
+            }
+            false => None,
+        };
+
+        let (split, charged) = self.fee_payout(
+            deps.storage,
+            &verifying_contract,
+            campaign_id.as_deref(),
+            &fee,
+        )?;
+        let refund = check_fee_payment(&info, &charged)?;
+
+        let record = ClaimRecord {
+            recipient: info.sender.to_owned(),
+            issuer: None,
+            verifying_contract: verifying_contract.to_owned(),
+            token_uri: token_uri.to_owned(),
+            fee: charged.to_owned(),
+            cw20_fee: None,
+            campaign_id,
+            referrer: None,
+            height: env.block.height,
+            time: env.block.time,
+        };
+        let claim_id = self.record_claim(deps.storage, &record)?;
+
+        let mint_msg = self.mint_msg(&info.sender, &token_uri, None, &verifying_contract)?;
+        let leaf = Binary::from(leaf);
+        let response = Response::new()
+            .add_event(claim_event(claim_id, &record, &[], None, &info.sender))
+            .add_attribute("action", "mint_with_merkle_proof")
+            .add_attribute("campaign", &campaign)
+            .add_attribute("leaf", leaf.to_base64())
+            .add_submessage(self.dispatch_mint(
+                deps.storage,
+                claim_id,
+                mint_msg,
+                PendingMint {
+                    sender: info.sender.to_owned(),
+                    source: MintSource::MerkleLeaf {
+                        verifying_contract,
+                        campaign,
+                        leaf,
+                        campaign_id: record.campaign_id,
+                    },
+                    split,
+                    fee: charged.to_owned(),
+                },
+            )?);
+
+        let refunds = refund_coins(&charged.denom, refund);
+        Ok(self.route_fees(response, &info.sender, vec![], refunds))
+    }
+
+    fn mint_with_claim_batch(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        claims: Vec<Claim>,
+        mode: BatchMode,
+    ) -> Result<Response<C>, ContractError> {
+        if claims.is_empty() || claims.len() > MAX_BATCH_SIZE {
+            return Err(ContractError::InvalidBatchSize {
+                max: MAX_BATCH_SIZE,
             });
         }
 
-        if message.to != _info.sender {
-            return Err(ContractError::NotReceiver {});
+        let mut response = Response::new().add_attribute("action", "mint_with_claim_batch");
+        let mut accepted = vec![];
+
+        // Accepted claims can still fail on the fee check or, in refund mode, be refunded when
+        // their mint fails, the `mercle_claim` and `mercle_claim_refund` events tell the outcome
+        for (index, claim) in claims.into_iter().enumerate() {
+            match self.accept_claim(deps.branch(), &env, &info, claim, None) {
+                Ok((message, signers)) => {
+                    response = response.add_attribute(format!("claim_{}", index), "accepted");
+                    accepted.push((message, signers));
+                }
+                Err(err) if mode == BatchMode::SkipInvalid => {
+                    response = response
+                        .add_attribute(format!("claim_{}", index), format!("skipped: {}", err));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut payouts = vec![];
+        for (message, _) in accepted.iter() {
+            payouts.push(self.fee_payout(
+                deps.storage,
+                &message.verifying_contract,
+                message.campaign_id.as_deref(),
+                &message.fee,
+            )?);
         }
 
-        let treasury = self.treasury.may_load(deps.storage).unwrap().unwrap();
+        let fees = sum_fees(
+            &payouts
+                .iter()
+                .map(|(_, fee)| fee.to_owned())
+                .collect::<Vec<Coin>>(),
+        );
+        let refunds = check_fees_payment(&info, &fees)?;
 
-        let mint_msg = MemberhsipExecute::Mint(MembershipMintMsg::<Empty> {
-            owner: message.to.into_string(),
-            token_uri: Some(message.token_uri.to_string()),
-            extension: Empty {},
-        });
+        let accepted_count = accepted.len();
+        for ((message, signers), (split, fee)) in accepted.into_iter().zip(payouts) {
+            let record = claim_record(&env, &message, &fee);
+            let claim_id = self.record_claim(deps.storage, &record)?;
+            response = response
+                .add_event(claim_event(
+                    claim_id,
+                    &record,
+                    &signers,
+                    message.extension.as_ref(),
+                    &info.sender,
+                ))
+                .add_submessage(self.dispatch_claim_mint(
+                    deps.storage,
+                    claim_id,
+                    &info.sender,
+                    message,
+                    split,
+                    fee,
+                )?);
+        }
+
+        let response = response.add_attribute("accepted", accepted_count.to_string());
+        Ok(self.route_fees(response, &info.sender, vec![], refunds))
+    }
+
+    // Verifies a claim submitted by its recipient or a permitted relayer and marks it used,
+    // returning the signed message and its issuers. The sender pays the fee and receives any
+    // refund.
+    fn accept_claim(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        claim: Claim,
+        cw20_paid: Option<&Cw20CoinVerified>,
+    ) -> Result<(Message, Vec<Addr>), ContractError> {
+        let signers = self.check_claim(deps.as_ref(), env, &info.sender, &claim, cw20_paid)?;
+        let Claim { message, .. } = claim;
+
+        self.mark_claim_used(deps.storage, &message)?;
+
+        Ok((message, signers))
+    }
 
-        let wasm_msg = WasmMsg::Execute {
-            contract_addr: message.verifying_contract.into_string(),
-            msg: to_json_binary(&mint_msg).unwrap(),
+    // Redeems a claim sent along with its cw20 fee, the cw20 sender takes the place of the
+    // message sender and the tokens go to the claim's treasury.
+    fn receive_cw20(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        wrapper: Cw20ReceiveMsg,
+    ) -> Result<Response<C>, ContractError> {
+        let claim = match from_json(&wrapper.msg)? {
+            Cw20HookMsg::MintWithClaim {
+                message,
+                signature,
+                recovery_byte,
+                sign_mode,
+                consent,
+                co_signatures,
+            } => Claim {
+                message,
+                signature,
+                recovery_byte,
+                sign_mode,
+                consent,
+                co_signatures,
+            },
+        };
+        let paid = Cw20CoinVerified {
+            address: info.sender,
+            amount: wrapper.amount,
+        };
+        let sender = MessageInfo {
+            sender: deps.api.addr_validate(&wrapper.sender)?,
             funds: vec![],
         };
 
-        let fund_transfer_msg = BankMsg::Send {
-            to_address: treasury.into_string(),
-            amount: vec![message.fee],
+        let (message, signers) =
+            self.accept_claim(deps.branch(), &env, &sender, claim, Some(&paid))?;
+
+        // The signed cw20 fee replaces the membership default, no native fee can be attached
+        let (split, _) = self.fee_payout(
+            deps.storage,
+            &message.verifying_contract,
+            message.campaign_id.as_deref(),
+            &message.fee,
+        )?;
+        check_fee_payment(&sender, &message.fee)?;
+
+        let fee = message.fee.to_owned();
+        let record = claim_record(&env, &message, &fee);
+        let claim_id = self.record_claim(deps.storage, &record)?;
+
+        Ok(Response::new()
+            .add_event(claim_event(
+                claim_id,
+                &record,
+                &signers,
+                message.extension.as_ref(),
+                &sender.sender,
+            ))
+            .add_submessage(self.dispatch_claim_mint(
+                deps.storage,
+                claim_id,
+                &sender.sender,
+                message,
+                split,
+                fee,
+            )?))
+    }
+
+    fn dispatch_claim_mint(
+        &self,
+        storage: &mut dyn Storage,
+        claim_id: u64,
+        sender: &Addr,
+        message: Message,
+        split: Vec<FeeShare>,
+        fee: Coin,
+    ) -> Result<SubMsg<C>, ContractError> {
+        let mint_msg = self.mint_msg(
+            &message.to,
+            &message.token_uri,
+            message.extension.as_ref(),
+            &message.verifying_contract,
+        )?;
+        let pending = PendingMint {
+            sender: sender.to_owned(),
+            source: MintSource::Claim(Box::new(message)),
+            split,
+            fee,
         };
+        self.dispatch_mint(storage, claim_id, mint_msg, pending)
+    }
 
-        self.claim_map
-            .save(deps.storage, &signature, &true)
-            .unwrap();
+    // Mints a recorded claim as a submessage replying with its claim id as reply id, the fee
+    // is held until the reply settles the claim.
+    fn dispatch_mint(
+        &self,
+        storage: &mut dyn Storage,
+        claim_id: u64,
+        mint_msg: CosmosMsg<C>,
+        pending: PendingMint,
+    ) -> Result<SubMsg<C>, ContractError> {
+        self.pending_mints.save(storage, claim_id, &pending)?;
+        // In revert mode a failed mint aborts the transaction with the membership contract's error
+        Ok(match self.get_mint_failure_mode(storage)? {
+            MintFailureMode::Revert => SubMsg::reply_on_success(mint_msg, claim_id),
+            MintFailureMode::Refund => SubMsg::reply_always(mint_msg, claim_id),
+        })
+    }
+
+    pub fn reply(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        msg: Reply,
+    ) -> Result<Response<C>, ContractError> {
+        let claim_id = msg.id;
+        let PendingMint {
+            sender,
+            source,
+            split,
+            fee,
+        } = self.pending_mints.load(deps.storage, claim_id)?;
+        self.pending_mints.remove(deps.storage, claim_id);
+
+        match msg.result {
+            SubMsgResult::Ok(_) => self.settle_claim(deps.storage, &sender, &source, split, fee),
+            // Only mints dispatched in refund mode reply on failure
+            SubMsgResult::Err(reason) => {
+                self.refund_claim(deps.storage, claim_id, &sender, &source, fee, reason)
+            }
+        }
+    }
+
+    // Pays out the fee of a claim whose mint succeeded
+    fn settle_claim(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        source: &MintSource,
+        split: Vec<FeeShare>,
+        fee: Coin,
+    ) -> Result<Response<C>, ContractError> {
+        let cw20_fee = match source.cw20_fee() {
+            Some(cw20_fee) => cw20_fee,
+            None => {
+                let payouts = self.claim_payouts(
+                    storage,
+                    source.verifying_contract(),
+                    source.referrer(),
+                    split,
+                    fee,
+                )?;
+                return Ok(self.route_fees(Response::new(), sender, payouts, vec![]));
+            }
+        };
+
+        let mut transfers = vec![];
+        let mut rest = cw20_fee.amount;
+        if let Some((referrer, reward)) =
+            self.referral_cut(storage, source.referrer(), cw20_fee.amount)?
+        {
+            let cw20_reward = Cw20CoinVerified {
+                address: cw20_fee.address.to_owned(),
+                amount: reward,
+            };
+            self.record_referral(storage, &referrer, None, Some(cw20_reward))?;
+            transfers.push((referrer, reward));
+            rest -= reward;
+        }
+
+        let revenue = Cw20CoinVerified {
+            address: cw20_fee.address.to_owned(),
+            amount: rest,
+        };
+        self.record_cw20_revenue(storage, source.verifying_contract(), &revenue)?;
+        if !self.is_vault_mode(storage)? {
+            transfers.extend(split_amount(rest, &split));
+        }
+
+        let mut response = Response::new();
+        for (recipient, amount) in transfers {
+            if amount.is_zero() {
+                continue;
+            }
+            response = response.add_message(cw20_transfer(&cw20_fee.address, &recipient, amount)?);
+        }
+        Ok(response)
+    }
+
+    // Gives the fee of a claim whose mint failed back to the sender and frees the claim
+    fn refund_claim(
+        &self,
+        storage: &mut dyn Storage,
+        claim_id: u64,
+        sender: &Addr,
+        source: &MintSource,
+        fee: Coin,
+        reason: String,
+    ) -> Result<Response<C>, ContractError> {
+        match source {
+            MintSource::Claim(message) => self.unmark_claim_used(storage, message)?,
+            MintSource::MerkleLeaf {
+                verifying_contract,
+                campaign,
+                leaf,
+                campaign_id,
+            } => {
+                self.used_leaves
+                    .remove(storage, (verifying_contract, campaign, leaf.as_slice()));
+                if let Some(campaign_id) = campaign_id {
+                    self.uncount_campaign_claim(storage, campaign_id, sender)?;
+                }
+            }
+        }
+        self.claim_records.remove(storage, claim_id)?;
+
+        let mut response = Response::new().add_event(claim_refund_event(claim_id, &reason));
+        if !fee.amount.is_zero() {
+            response = response.add_message(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![fee],
+            });
+        }
+        if let Some(cw20_fee) = source.cw20_fee() {
+            if !cw20_fee.amount.is_zero() {
+                response = response.add_message(cw20_transfer(
+                    &cw20_fee.address,
+                    sender,
+                    cw20_fee.amount,
+                )?);
+            }
+        }
+        Ok(response)
+    }
+
+    // Pays the referral share of a claim's fee to its referrer. The rest is revenue of the
+    // membership contract and goes to the fee split, or stays in the vault in vault mode.
+    fn claim_payouts(
+        &self,
+        storage: &mut dyn Storage,
+        verifying_contract: &Addr,
+        referrer: Option<&Addr>,
+        split: Vec<FeeShare>,
+        fee: Coin,
+    ) -> StdResult<Vec<(Vec<FeeShare>, Coin)>> {
+        let mut payouts = vec![];
+        let mut rest = fee;
+
+        if let Some((referrer, reward)) = self.referral_cut(storage, referrer, rest.amount)? {
+            let reward = Coin {
+                denom: rest.denom.to_owned(),
+                amount: reward,
+            };
+            self.record_referral(storage, &referrer, Some(reward.to_owned()), None)?;
+            rest.amount -= reward.amount;
+            let referrer = vec![FeeShare {
+                address: referrer,
+                bps: FULL_BPS,
+            }];
+            payouts.push((referrer, reward));
+        }
+
+        self.record_revenue(storage, verifying_contract, &rest)?;
+        if !self.is_vault_mode(storage)? {
+            payouts.push((split, rest));
+        }
+        Ok(payouts)
+    }
+
+    fn mint_msg(
+        &self,
+        owner: &Addr,
+        token_uri: &str,
+        extension: Option<&Binary>,
+        verifying_contract: &Addr,
+    ) -> Result<CosmosMsg<C>, ContractError> {
+        let extension = parse_extension(extension)?;
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: verifying_contract.to_string(),
+            msg: membership_mint_msg(owner, token_uri, extension)?,
+            funds: vec![],
+        }))
+    }
+
+    // Sends each fee to its split recipients, one transfer per recipient, and overpayments
+    // back to the sender
+    fn route_fees(
+        &self,
+        mut response: Response<C>,
+        sender: &Addr,
+        payouts: Vec<(Vec<FeeShare>, Coin)>,
+        refunds: Vec<Coin>,
+    ) -> Response<C> {
+        let mut by_treasury: Vec<(Addr, Vec<Coin>)> = vec![];
+        for (split, fee) in payouts {
+            for (treasury, amount) in split_amount(fee.amount, &split) {
+                let fee = Coin {
+                    denom: fee.denom.to_owned(),
+                    amount,
+                };
+                match by_treasury.iter_mut().find(|(addr, _)| *addr == treasury) {
+                    Some((_, fees)) => fees.push(fee),
+                    None => by_treasury.push((treasury, vec![fee])),
+                }
+            }
+        }
+
+        for (treasury, fees) in by_treasury {
+            let amount = sum_fees(&fees);
+            if !amount.is_empty() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: treasury.into_string(),
+                    amount,
+                });
+            }
+        }
+
+        if !refunds.is_empty() {
+            response = response.add_message(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: refunds,
+            });
+        }
+
+        response
+    }
+
+    fn revoke_claims(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        revocations: Vec<ClaimRevocation>,
+    ) -> Result<Response<C>, ContractError> {
+        if revocations.is_empty() || revocations.len() > MAX_REVOCATIONS {
+            return Err(ContractError::InvalidRevocationSize {
+                max: MAX_REVOCATIONS,
+            });
+        }
+
+        let is_admin = self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default();
+        let version = self.get_digest_version(deps.storage)?;
+        let count = revocations.len();
+
+        for revocation in revocations {
+            match revocation {
+                ClaimRevocation::Message { message } => {
+                    if !is_admin && message.from != info.sender {
+                        return Err(ContractError::Unauthorized {});
+                    }
+                    let digest = message_digest(&message, version)?;
+                    self.revoked_digests.save(deps.storage, &digest, &true)?;
+                }
+                ClaimRevocation::Nonce { issuer, nonce } => {
+                    if !is_admin && issuer != info.sender {
+                        return Err(ContractError::Unauthorized {});
+                    }
+                    self.revoked_nonces
+                        .save(deps.storage, (&issuer, nonce), &true)?;
+                }
+                ClaimRevocation::Digest { digest } => {
+                    if !is_admin {
+                        return Err(ContractError::Unauthorized {});
+                    }
+                    self.revoked_digests.save(deps.storage, &digest, &true)?;
+                }
+            }
+        }
 
         Ok(Response::new()
-            .add_message(CosmosMsg::Wasm(wasm_msg))
-            .add_message(fund_transfer_msg))
+            .add_attribute("action", "revoke_claims")
+            .add_attribute("revoked", count.to_string()))
     }
 
     fn set_treasury(
@@ -117,7 +811,362 @@ where
             return Err(ContractError::Unauthorized {});
         }
         self.treasury.save(_deps.storage, &address).unwrap();
-        Ok(Response::default())
+        Ok(Response::new().add_event(treasury_event(&address, &_info.sender)))
+    }
+
+    fn set_fee_split(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        split: Option<Vec<FeeShare>>,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        match &split {
+            Some(split) => {
+                validate_fee_split(deps.as_ref(), split)?;
+                self.fee_split.save(deps.storage, split)?;
+            }
+            None => self.fee_split.remove(deps.storage),
+        }
+        Ok(Response::new().add_event(fee_split_event(&split.unwrap_or_default(), &info.sender)))
+    }
+
+    fn set_referral_share(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        bps: Option<u16>,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        match bps {
+            Some(bps) if bps > FULL_BPS => return Err(ContractError::InvalidReferralShare {}),
+            Some(bps) => self.referral_bps.save(deps.storage, &bps)?,
+            None => self.referral_bps.remove(deps.storage),
+        }
+        Ok(Response::new()
+            .add_attribute("action", "set_referral_share")
+            .add_attribute("bps", bps.unwrap_or_default().to_string()))
+    }
+
+    fn set_vault_mode(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        enabled: bool,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.vault_mode.save(deps.storage, &enabled)?;
+        Ok(Response::new().add_event(vault_event(enabled, &info.sender)))
+    }
+
+    fn withdraw(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        denom: String,
+        amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let recipient = deps.api.addr_validate(recipient.as_str())?;
+
+        let balance = self
+            .vault_balances
+            .may_load(deps.storage, &denom)?
+            .unwrap_or_default();
+        if amount.is_zero() || amount > balance {
+            return Err(ContractError::InsufficientVaultBalance {
+                available: Coin {
+                    denom,
+                    amount: balance,
+                },
+            });
+        }
+        self.vault_balances
+            .save(deps.storage, &denom, &(balance - amount))?;
+
+        let amount = Coin { denom, amount };
+        Ok(Response::new()
+            .add_event(withdraw_event(&recipient, &amount, &info.sender))
+            .add_message(BankMsg::Send {
+                to_address: recipient.into_string(),
+                amount: vec![amount],
+            }))
+    }
+
+    fn withdraw_cw20(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        token: Addr,
+        amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let recipient = deps.api.addr_validate(recipient.as_str())?;
+
+        let balance = self
+            .cw20_vault_balances
+            .may_load(deps.storage, &token)?
+            .unwrap_or_default();
+        if amount.is_zero() || amount > balance {
+            return Err(ContractError::InsufficientCw20VaultBalance {
+                available: Cw20CoinVerified {
+                    address: token,
+                    amount: balance,
+                },
+            });
+        }
+        self.cw20_vault_balances
+            .save(deps.storage, &token, &(balance - amount))?;
+
+        let amount = Cw20CoinVerified {
+            address: token,
+            amount,
+        };
+        Ok(Response::new()
+            .add_event(cw20_withdraw_event(&recipient, &amount, &info.sender))
+            .add_message(cw20_transfer(&amount.address, &recipient, amount.amount)?))
+    }
+
+    fn set_merkle_root(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        verifying_contract: Addr,
+        campaign: String,
+        root: Option<Binary>,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let key = (&verifying_contract, campaign.as_str());
+        match &root {
+            Some(root) if root.len() == 32 => self.merkle_roots.save(deps.storage, key, root)?,
+            Some(_) => return Err(ContractError::InvalidMerkleRoot {}),
+            None => self.merkle_roots.remove(deps.storage, key),
+        }
+        Ok(Response::new().add_event(merkle_root_event(
+            &verifying_contract,
+            &campaign,
+            root.as_ref(),
+            &info.sender,
+        )))
+    }
+
+    fn register_membership_contract(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        contract: Addr,
+        config: MembershipContract,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let contract = deps.api.addr_validate(contract.as_str())?;
+        if let Some(treasury) = &config.treasury {
+            deps.api.addr_validate(treasury.as_str())?;
+        }
+        if let Some(split) = &config.fee_split {
+            validate_fee_split(deps.as_ref(), split)?;
+        }
+        self.membership_contracts
+            .save(deps.storage, &contract, &config)?;
+        Ok(Response::new()
+            .add_attribute("action", "register_membership_contract")
+            .add_attribute("contract", contract))
+    }
+
+    fn remove_membership_contract(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        contract: Addr,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.membership_contracts.remove(deps.storage, &contract);
+        Ok(Response::new()
+            .add_attribute("action", "remove_membership_contract")
+            .add_attribute("contract", contract))
+    }
+
+    fn create_campaign(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        id: String,
+        config: CampaignConfig,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        validate_campaign_config(deps.as_ref(), &config)?;
+        if self.campaigns.has(deps.storage, &id) {
+            return Err(ContractError::CampaignExists { id });
+        }
+
+        let campaign = Campaign {
+            config,
+            closed: false,
+            claimed: 0,
+        };
+        self.campaigns.save(deps.storage, &id, &campaign)?;
+        Ok(Response::new()
+            .add_attribute("action", "create_campaign")
+            .add_attribute("campaign", id))
+    }
+
+    fn update_campaign(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        id: String,
+        config: CampaignConfig,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        validate_campaign_config(deps.as_ref(), &config)?;
+
+        let mut campaign = self
+            .campaigns
+            .may_load(deps.storage, &id)?
+            .ok_or_else(|| ContractError::CampaignNotFound { id: id.to_owned() })?;
+        campaign.config = config;
+        self.campaigns.save(deps.storage, &id, &campaign)?;
+        Ok(Response::new()
+            .add_attribute("action", "update_campaign")
+            .add_attribute("campaign", id))
+    }
+
+    fn close_campaign(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        id: String,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let mut campaign = self
+            .campaigns
+            .may_load(deps.storage, &id)?
+            .ok_or_else(|| ContractError::CampaignNotFound { id: id.to_owned() })?;
+        campaign.closed = true;
+        self.campaigns.save(deps.storage, &id, &campaign)?;
+        Ok(Response::new()
+            .add_attribute("action", "close_campaign")
+            .add_attribute("campaign", id))
+    }
+
+    fn set_max_claim_validity(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        seconds: Option<u64>,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        match seconds {
+            Some(seconds) => self.max_claim_validity.save(deps.storage, &seconds)?,
+            None => self.max_claim_validity.remove(deps.storage),
+        }
+        Ok(Response::new().add_event(config_event(
+            "max_claim_validity",
+            seconds.map(|seconds| seconds.to_string()),
+            &info.sender,
+        )))
+    }
+
+    fn set_digest_version(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        version: DigestVersion,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.digest_version.save(deps.storage, &version)?;
+        Ok(Response::new().add_event(config_event(
+            "digest_version",
+            Some(format!("{:?}", version)),
+            &info.sender,
+        )))
+    }
+
+    fn set_mint_failure_mode(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        mode: MintFailureMode,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.mint_failure_mode.save(deps.storage, &mode)?;
+        Ok(Response::new().add_event(config_event(
+            "mint_failure_mode",
+            Some(format!("{:?}", mode)),
+            &info.sender,
+        )))
     }
 
     pub fn grant_role(
@@ -136,7 +1185,7 @@ where
         self.update_role(deps.storage, &address, role, true)
             .unwrap();
 
-        Ok(Response::new())
+        Ok(Response::new().add_event(role_event("grant", role, &address, &info.sender)))
     }
 
     pub fn revoke_role(
@@ -155,6 +1204,203 @@ where
         self.update_role(deps.storage, &address, role, false)
             .unwrap();
 
-        Ok(Response::new())
+        Ok(Response::new().add_event(role_event("revoke", role, &address, &info.sender)))
+    }
+
+    fn register_issuer_key(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        key: Binary,
+        issuer_key: IssuerKey,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        if key.len() != issuer_key.key_type.key_len() {
+            return Err(ContractError::InvalidIssuerKey {});
+        }
+
+        self.issuer_keys.save(deps.storage, &key, &issuer_key)?;
+        Ok(Response::new().add_event(issuer_key_event(
+            "register",
+            &key,
+            &issuer_key,
+            None,
+            &info.sender,
+        )))
+    }
+
+    fn revoke_issuer_key(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        key: Binary,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let mut issuer_key = self
+            .issuer_keys
+            .may_load(deps.storage, &key)?
+            .ok_or(ContractError::IssuerKeyNotRegistered {})?;
+        issuer_key.revoked = true;
+        self.issuer_keys.save(deps.storage, &key, &issuer_key)?;
+        Ok(Response::new().add_event(issuer_key_event(
+            "revoke",
+            &key,
+            &issuer_key,
+            None,
+            &info.sender,
+        )))
+    }
+
+    // Revokes `key` and registers `new_key` for the same issuer, label and scope
+    fn rotate_issuer_key(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        key: Binary,
+        new_key_type: KeyType,
+        new_key: Binary,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        if new_key.len() != new_key_type.key_len() || self.issuer_keys.has(deps.storage, &new_key) {
+            return Err(ContractError::InvalidIssuerKey {});
+        }
+
+        let old_key = self
+            .issuer_keys
+            .may_load(deps.storage, &key)?
+            .ok_or(ContractError::IssuerKeyNotRegistered {})?;
+
+        let new_issuer_key = IssuerKey {
+            key_type: new_key_type,
+            valid_from: Some(env.block.time),
+            ..old_key.clone()
+        };
+        self.issuer_keys.save(
+            deps.storage,
+            &key,
+            &IssuerKey {
+                revoked: true,
+                ..old_key
+            },
+        )?;
+        self.issuer_keys
+            .save(deps.storage, &new_key, &new_issuer_key)?;
+
+        Ok(Response::new().add_event(issuer_key_event(
+            "rotate",
+            &key,
+            &new_issuer_key,
+            Some(&new_key),
+            &info.sender,
+        )))
+    }
+
+    fn remove_issuer_key(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        key: Binary,
+    ) -> Result<Response<C>, ContractError> {
+        if !self
+            .has_role(deps.storage, &info.sender, Role::DefaultAdmin)
+            .unwrap_or_default()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let issuer_key = self
+            .issuer_keys
+            .may_load(deps.storage, &key)?
+            .ok_or(ContractError::IssuerKeyNotRegistered {})?;
+        self.issuer_keys.remove(deps.storage, &key)?;
+        Ok(Response::new().add_event(issuer_key_event(
+            "remove",
+            &key,
+            &issuer_key,
+            None,
+            &info.sender,
+        )))
+    }
+}
+
+fn validate_campaign_config(deps: Deps, config: &CampaignConfig) -> Result<(), ContractError> {
+    deps.api.addr_validate(config.verifying_contract.as_str())?;
+    if let Some(treasury) = &config.treasury {
+        deps.api.addr_validate(treasury.as_str())?;
+    }
+    if let (Some(start), Some(end)) = (config.start_time, config.end_time) {
+        if end <= start {
+            return Err(ContractError::InvalidCampaignConfig {});
+        }
+    }
+    if config.max_claims == Some(0) || config.max_claims_per_address == Some(0) {
+        return Err(ContractError::InvalidCampaignConfig {});
+    }
+    Ok(())
+}
+
+fn validate_fee_split(deps: Deps, split: &[FeeShare]) -> Result<(), ContractError> {
+    let invalid = ContractError::InvalidFeeSplit {
+        max: MAX_FEE_RECIPIENTS,
+    };
+    if split.is_empty() || split.len() > MAX_FEE_RECIPIENTS {
+        return Err(invalid);
+    }
+
+    let mut total: u32 = 0;
+    for (index, share) in split.iter().enumerate() {
+        deps.api.addr_validate(share.address.as_str())?;
+        if share.bps == 0 || split[..index].iter().any(|s| s.address == share.address) {
+            return Err(invalid);
+        }
+        total += share.bps as u32;
+    }
+    if total != FULL_BPS as u32 {
+        return Err(invalid);
+    }
+    Ok(())
+}
+
+fn cw20_transfer(token: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    })
+}
+
+fn claim_record(env: &Env, message: &Message, fee: &Coin) -> ClaimRecord {
+    ClaimRecord {
+        recipient: message.to.to_owned(),
+        issuer: Some(message.from.to_owned()),
+        verifying_contract: message.verifying_contract.to_owned(),
+        token_uri: message.token_uri.to_owned(),
+        fee: fee.to_owned(),
+        cw20_fee: message.cw20_fee.to_owned(),
+        campaign_id: message.campaign_id.to_owned(),
+        referrer: message.referrer.to_owned(),
+        height: env.block.height,
+        time: env.block.time,
     }
 }
@@ -1,11 +1,16 @@
-use crate::msg::Message;
-use crate::state::Role;
+use crate::error::ContractError;
+use crate::msg::{MemberhsipExecute, MembershipMintMsg, Message};
+use crate::state::{DigestVersion, FeeShare, Role, FULL_BPS};
 use bech32::{encode, ToBase32};
-use cosmwasm_std::{Addr, Binary, Deps, StdError, StdResult};
+use cosmwasm_std::{Addr, Binary, Coin, Deps, MessageInfo, StdError, StdResult, Uint128};
+use cw20::Cw20CoinVerified;
+use cw_utils::{must_pay, nonpayable, PaymentError};
 use ripemd160::Digest as OtherDigest;
 use ripemd160::Ripemd160;
+use serde_json::value::RawValue;
 use sha2::Digest;
 use sha2::Sha256;
+use tiny_keccak::{Hasher, Keccak};
 
 pub fn get_key_for_role<'a>(role: Role) -> &'a str {
     match role {
@@ -16,35 +21,427 @@ pub fn get_key_for_role<'a>(role: Role) -> &'a str {
     }
 }
 
+// Checks the attached funds against the signed fee and returns the overpaid amount.
+pub fn check_fee_payment(info: &MessageInfo, fee: &Coin) -> Result<Uint128, ContractError> {
+    if fee.amount.is_zero() {
+        nonpayable(info)?;
+        return Ok(Uint128::zero());
+    }
+
+    let paid = must_pay(info, &fee.denom)?;
+    if paid < fee.amount {
+        return Err(ContractError::InsufficientFee {
+            required: fee.to_owned(),
+            paid,
+        });
+    }
+
+    Ok(paid - fee.amount)
+}
+
+/// Checks a claim's `extension` is a JSON object and keeps its signed bytes as they are, an unset
+/// extension is forwarded as `{}`.
+pub fn parse_extension(extension: Option<&Binary>) -> Result<Box<RawValue>, ContractError> {
+    let invalid = |msg: String| ContractError::InvalidExtension { msg };
+    let extension = match extension {
+        Some(extension) => serde_json::from_slice::<Box<RawValue>>(extension)
+            .map_err(|err| invalid(err.to_string()))?,
+        None => RawValue::from_string("{}".to_string()).map_err(|err| invalid(err.to_string()))?,
+    };
+    // Membership contracts decode the extension as a struct
+    if !extension.get().starts_with('{') {
+        return Err(invalid("expected an object".to_string()));
+    }
+    Ok(extension)
+}
+
+/// Membership `Mint` message carrying the claim extension verbatim.
+pub fn membership_mint_msg(
+    owner: &Addr,
+    token_uri: &str,
+    extension: Box<RawValue>,
+) -> StdResult<Binary> {
+    let mint_msg = MemberhsipExecute::Mint(MembershipMintMsg::<Box<RawValue>> {
+        owner: owner.to_string(),
+        token_uri: Some(token_uri.to_string()),
+        extension,
+    });
+    // serde_json rather than `to_json_binary`, which cannot encode raw JSON values
+    let encoded = serde_json::to_vec(&mint_msg)
+        .map_err(|err| StdError::serialize_err("MembershipMintMsg", err))?;
+    Ok(Binary::from(encoded))
+}
+
+// Sums fees per denom, keeping the order in which denoms first appear.
+pub fn sum_fees(fees: &[Coin]) -> Vec<Coin> {
+    let mut total: Vec<Coin> = vec![];
+    for fee in fees.iter().filter(|fee| !fee.amount.is_zero()) {
+        match total.iter_mut().find(|coin| coin.denom == fee.denom) {
+            Some(coin) => coin.amount += fee.amount,
+            None => total.push(fee.to_owned()),
+        }
+    }
+    total
+}
+
+pub fn refund_coins(denom: &str, refund: Uint128) -> Vec<Coin> {
+    if refund.is_zero() {
+        return vec![];
+    }
+    vec![Coin {
+        denom: denom.to_string(),
+        amount: refund,
+    }]
+}
+
+// Divides `amount` by the split weights, rounding each share down. The rounding remainder goes
+// to the first recipient so the shares always add up to `amount`.
+pub fn split_amount(amount: Uint128, split: &[FeeShare]) -> Vec<(Addr, Uint128)> {
+    let mut shares: Vec<(Addr, Uint128)> = split
+        .iter()
+        .map(|share| {
+            let part = amount.multiply_ratio(share.bps, FULL_BPS);
+            (share.address.to_owned(), part)
+        })
+        .collect();
+
+    let distributed: Uint128 = shares.iter().map(|(_, part)| *part).sum();
+    if let Some((_, first)) = shares.first_mut() {
+        *first += amount - distributed;
+    }
+    shares
+}
+
+// Checks the attached funds cover every required coin and returns the overpaid coins.
+pub fn check_fees_payment(info: &MessageInfo, fees: &[Coin]) -> Result<Vec<Coin>, ContractError> {
+    if fees.is_empty() {
+        nonpayable(info)?;
+        return Ok(vec![]);
+    }
+
+    if let Some(extra) = info
+        .funds
+        .iter()
+        .find(|paid| !fees.iter().any(|fee| fee.denom == paid.denom))
+    {
+        return Err(PaymentError::ExtraDenom(extra.denom.to_owned()).into());
+    }
+
+    let mut refunds = vec![];
+    for fee in fees {
+        let paid = info
+            .funds
+            .iter()
+            .filter(|coin| coin.denom == fee.denom)
+            .fold(Uint128::zero(), |acc, coin| acc + coin.amount);
+        if paid < fee.amount {
+            return Err(ContractError::InsufficientFee {
+                required: fee.to_owned(),
+                paid,
+            });
+        }
+        if paid > fee.amount {
+            refunds.push(Coin {
+                denom: fee.denom.to_owned(),
+                amount: paid - fee.amount,
+            });
+        }
+    }
+
+    Ok(refunds)
+}
+
+pub const CLAIM_DOMAIN_NAME: &str = "MercleMintWithClaim";
+pub const CLAIM_DOMAIN_VERSION: &str = "1";
+pub const CLAIM_CONSENT_PREFIX: &str = "MercleClaimConsent";
+
+// Distinct leaf and node prefixes keep an inner node from being passed off as a leaf
+const MERKLE_LEAF_PREFIX: u8 = 0x00;
+const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+const DOMAIN_TYPE: &str =
+    "MercleDomain(string name,string version,string chain_id,address contract)";
+const CLAIM_TYPE: &str = "MercleClaim(address from,address to,string token_uri,string fee_denom,uint128 fee_amount,address verifying_contract,string bech32_hre,uint64 issued_at,uint64 expires_at,uint64? nonce,bool? relayable,string? campaign_id,bytes? extension,(address,uint128)? cw20_fee,address? referrer)";
+
+// secp256k1 curve order divided by two, signatures with a larger s are malleable
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+fn hash_str(value: &str) -> [u8; 32] {
+    Sha256::digest(value.as_bytes()).into()
+}
+
+// Optional integers are encoded as a presence byte followed by the value when set
+fn encode_optional_u64(encoded: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            encoded.push(1);
+            encoded.extend_from_slice(&value.to_be_bytes());
+        }
+        None => encoded.push(0),
+    }
+}
+
+fn encode_optional_bool(encoded: &mut Vec<u8>, value: Option<bool>) {
+    match value {
+        Some(value) => encoded.extend_from_slice(&[1, value as u8]),
+        None => encoded.push(0),
+    }
+}
+
+fn encode_optional_str(encoded: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            encoded.push(1);
+            encoded.extend_from_slice(&hash_str(value));
+        }
+        None => encoded.push(0),
+    }
+}
+
+fn encode_optional_bytes(encoded: &mut Vec<u8>, value: Option<&[u8]>) {
+    match value {
+        Some(value) => {
+            encoded.push(1);
+            encoded.extend_from_slice(&Sha256::digest(value));
+        }
+        None => encoded.push(0),
+    }
+}
+
+fn encode_optional_cw20(encoded: &mut Vec<u8>, value: Option<&Cw20CoinVerified>) {
+    match value {
+        Some(value) => {
+            encoded.push(1);
+            encoded.extend_from_slice(&hash_str(value.address.as_str()));
+            encoded.extend_from_slice(&value.amount.u128().to_be_bytes());
+        }
+        None => encoded.push(0),
+    }
+}
+
+/// Domain separator binding claims to a name, version, chain and MintWithClaim instance.
+pub fn claim_domain_separator(chain_id: &str, contract: &Addr) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(32 * 5);
+    encoded.extend_from_slice(&hash_str(DOMAIN_TYPE));
+    encoded.extend_from_slice(&hash_str(CLAIM_DOMAIN_NAME));
+    encoded.extend_from_slice(&hash_str(CLAIM_DOMAIN_VERSION));
+    encoded.extend_from_slice(&hash_str(chain_id));
+    encoded.extend_from_slice(&hash_str(contract.as_str()));
+    Sha256::digest(encoded).into()
+}
+
+/// Canonical digest of a claim:
+/// `sha256(0x19 0x01 || domain_separator || struct_hash)`, where every string field of
+/// the struct hash is encoded as its sha256, integers as fixed-width big-endian bytes and
+/// optional values with a leading presence byte.
+pub fn claim_digest(message: &Message) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(32 * 10 + 16 + 8 * 3 + 5);
+    encoded.extend_from_slice(&hash_str(CLAIM_TYPE));
+    encoded.extend_from_slice(&hash_str(message.from.as_str()));
+    encoded.extend_from_slice(&hash_str(message.to.as_str()));
+    encoded.extend_from_slice(&hash_str(&message.token_uri));
+    encoded.extend_from_slice(&hash_str(&message.fee.denom));
+    encoded.extend_from_slice(&message.fee.amount.u128().to_be_bytes());
+    encoded.extend_from_slice(&hash_str(message.verifying_contract.as_str()));
+    encoded.extend_from_slice(&hash_str(&message.bech32_hre));
+    encoded.extend_from_slice(&message.issued_at.nanos().to_be_bytes());
+    encoded.extend_from_slice(&message.expires_at.nanos().to_be_bytes());
+    encode_optional_u64(&mut encoded, message.nonce);
+    encode_optional_bool(&mut encoded, message.relayable);
+    encode_optional_str(&mut encoded, message.campaign_id.as_deref());
+    encode_optional_bytes(&mut encoded, message.extension.as_deref());
+    encode_optional_cw20(&mut encoded, message.cw20_fee.as_ref());
+    encode_optional_str(&mut encoded, message.referrer.as_ref().map(Addr::as_str));
+    let struct_hash: [u8; 32] = Sha256::digest(encoded).into();
+
+    let domain_separator = claim_domain_separator(&message.chain_id, &message.claim_contract);
+
+    let mut digest_input = Vec::with_capacity(2 + 32 * 2);
+    digest_input.extend_from_slice(&[0x19, 0x01]);
+    digest_input.extend_from_slice(&domain_separator);
+    digest_input.extend_from_slice(&struct_hash);
+    Sha256::digest(digest_input).into()
+}
+
+/// Digest a recipient signs to let a relayer submit the claim with the given digest.
+pub fn consent_digest(claim_digest: &[u8]) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(CLAIM_CONSENT_PREFIX.len() + claim_digest.len());
+    encoded.extend_from_slice(CLAIM_CONSENT_PREFIX.as_bytes());
+    encoded.extend_from_slice(claim_digest);
+    Sha256::digest(encoded).into()
+}
+
+/// Leaf of a Merkle allowlist: `sha256(0x00 || sha256(address) || sha256(token_uri) ||
+/// sha256(fee_denom) || fee_amount)`, with the amount as 16 big-endian bytes.
+pub fn merkle_leaf(address: &Addr, token_uri: &str, fee: &Coin) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(1 + 32 * 3 + 16);
+    encoded.push(MERKLE_LEAF_PREFIX);
+    encoded.extend_from_slice(&hash_str(address.as_str()));
+    encoded.extend_from_slice(&hash_str(token_uri));
+    encoded.extend_from_slice(&hash_str(&fee.denom));
+    encoded.extend_from_slice(&fee.amount.u128().to_be_bytes());
+    Sha256::digest(encoded).into()
+}
+
+/// Walks `proof` from `leaf` up to `root`. Each node is `sha256(0x01 || min(a, b) || max(a, b))`,
+/// so proofs carry no left / right flags.
+pub fn verify_merkle_proof(root: &[u8], leaf: [u8; 32], proof: &[Binary]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        let sibling: [u8; 32] = match sibling.as_slice().try_into() {
+            Ok(sibling) => sibling,
+            Err(_) => return false,
+        };
+        let (left, right) = if node <= sibling {
+            (node, sibling)
+        } else {
+            (sibling, node)
+        };
+        let mut encoded = Vec::with_capacity(1 + 32 * 2);
+        encoded.push(MERKLE_NODE_PREFIX);
+        encoded.extend_from_slice(&left);
+        encoded.extend_from_slice(&right);
+        node = Sha256::digest(encoded).into();
+    }
+    node[..] == root[..]
+}
+
+/// Pre-`V1` digest: sha256 over the serde_json encoding of the message, with its fields in
+/// declaration order: `from`, `to`, `token_uri`, `fee`, `verifying_contract`, `claim_contract`,
+/// `chain_id`, `bech32_hre`, `issued_at`, `expires_at`, then `nonce`, `relayable`,
+/// `campaign_id`, `extension`, `cw20_fee` and `referrer` when set. Timestamps encode as
+/// nanosecond strings. This keeps the hashing of the old scheme, not its field set: claims
+/// signed over the old `timestamp` field no longer verify and must be signed again.
+pub fn legacy_claim_digest(message: &Message) -> StdResult<[u8; 32]> {
+    let message_json =
+        serde_json::to_string(message).map_err(|err| StdError::serialize_err("Message", err))?;
+    Ok(Sha256::digest(message_json).into())
+}
+
+pub fn message_digest(message: &Message, version: DigestVersion) -> StdResult<[u8; 32]> {
+    match version {
+        DigestVersion::Legacy => legacy_claim_digest(message),
+        DigestVersion::V1 => Ok(claim_digest(message)),
+    }
+}
+
+// Rejects the high-s half of secp256k1 signatures so each message has one valid signature
+pub fn is_low_s_signature(signature: &[u8]) -> bool {
+    signature.len() == 64 && signature[32..] <= SECP256K1_HALF_ORDER[..]
+}
+
 pub fn recover_signer(
     deps: Deps,
-    message: Message,
-    signature: Binary,
+    hash: &[u8],
+    bech32_hre: &str,
+    signature: &Binary,
     recovery_byte: u8,
 ) -> StdResult<Addr> {
-    let message_hash = serde_json::to_string(&message).unwrap();
-    let hash = Sha256::digest(message_hash.clone());
+    let pub_key = recover_pubkey(deps, hash, signature, recovery_byte)?;
+    pubkey_to_address(&pub_key, bech32_hre)
+}
 
-    let result = deps
+// Recovers the compressed secp256k1 public key behind `signature`
+pub fn recover_pubkey(
+    deps: Deps,
+    hash: &[u8],
+    signature: &Binary,
+    recovery_byte: u8,
+) -> StdResult<Vec<u8>> {
+    let pub_key_uncompressed = deps
         .api
-        .secp256k1_recover_pubkey(hash.as_ref(), &signature, recovery_byte);
-    match result {
-        Ok(pub_key_uncompressed) => {
-            let pub_key = compress_pubkey(&pub_key_uncompressed)?;
-
-            let sha256_hash = Sha256::digest(&pub_key);
-            let ripemd160_hash = Ripemd160::digest(&sha256_hash);
-
-            let address_bytes = ripemd160_hash.to_base32();
-            let bech32_addr = encode(&message.bech32_hre, address_bytes)
-                .map_err(|err| StdError::generic_err(format!("Bech32 encoding failed: {}", err)))?;
-            let address = Addr::unchecked(bech32_addr);
-            Ok(address)
-        }
-        Err(err) => Err(err.into()),
+        .secp256k1_recover_pubkey(hash, signature, recovery_byte)?;
+    compress_pubkey(&pub_key_uncompressed)
+}
+
+// Brings a secp256k1 public key into its 33 byte compressed form
+pub fn normalize_pubkey(pub_key: &[u8]) -> StdResult<Vec<u8>> {
+    match pub_key.len() {
+        33 => Ok(pub_key.to_vec()),
+        _ => compress_pubkey(pub_key),
     }
 }
 
+// Derives the bech32 account address of a secp256k1 public key
+pub fn pubkey_to_address(pub_key: &[u8], bech32_hre: &str) -> StdResult<Addr> {
+    let pub_key = normalize_pubkey(pub_key)?;
+
+    let sha256_hash = Sha256::digest(&pub_key);
+    let ripemd160_hash = Ripemd160::digest(&sha256_hash);
+
+    let address_bytes = ripemd160_hash.to_base32();
+    let bech32_addr = encode(bech32_hre, address_bytes)
+        .map_err(|err| StdError::generic_err(format!("Bech32 encoding failed: {}", err)))?;
+    Ok(Addr::unchecked(bech32_addr))
+}
+
+// Amino JSON sign doc produced by wallets' `signArbitrary` (ADR-036), keys sorted
+pub fn adr036_sign_doc(signer: &Addr, data: &[u8]) -> String {
+    format!(
+        r#"{{"account_number":"0","chain_id":"","fee":{{"amount":[],"gas":"0"}},"memo":"","msgs":[{{"type":"sign/MsgSignData","value":{{"data":"{}","signer":"{}"}}}}],"sequence":"0"}}"#,
+        Binary::from(data).to_base64(),
+        signer
+    )
+}
+
+pub fn verify_adr036_signature(
+    deps: Deps,
+    signer: &Addr,
+    data: &[u8],
+    signature: &Binary,
+    pub_key: &Binary,
+) -> StdResult<bool> {
+    let sign_doc = adr036_sign_doc(signer, data);
+    let hash = Sha256::digest(sign_doc.as_bytes());
+    Ok(deps.api.secp256k1_verify(&hash, signature, pub_key)?)
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+// EIP-191 `personal_sign` hash of arbitrary data
+pub fn eip191_hash(data: &[u8]) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", data.len()).into_bytes();
+    prefixed.extend_from_slice(data);
+    keccak256(&prefixed)
+}
+
+// Recovers the 20-byte Ethereum address that produced `signature` over `hash`
+pub fn recover_eth_address(
+    deps: Deps,
+    hash: &[u8],
+    signature: &Binary,
+    recovery_byte: u8,
+) -> StdResult<[u8; 20]> {
+    // Ethereum tooling reports v as 27 / 28
+    let recovery_byte = if recovery_byte >= 27 {
+        recovery_byte - 27
+    } else {
+        recovery_byte
+    };
+
+    let pub_key = deps
+        .api
+        .secp256k1_recover_pubkey(hash, signature, recovery_byte)?;
+    if pub_key.len() != 65 || pub_key[0] != 0x04 {
+        return Err(StdError::generic_err(
+            "Invalid uncompressed public key format",
+        ));
+    }
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&keccak256(&pub_key[1..])[12..]);
+    Ok(address)
+}
+
 // Function to compress a public key
 fn compress_pubkey(pub_key: &[u8]) -> StdResult<Vec<u8>> {
     if pub_key.len() != 65 || pub_key[0] != 0x04 {
@@ -54,7 +451,7 @@ fn compress_pubkey(pub_key: &[u8]) -> StdResult<Vec<u8>> {
     }
 
     let mut compressed = vec![0u8; 33];
-    compressed[0] = if pub_key[64] % 2 == 0 { 0x02 } else { 0x03 };
+    compressed[0] = if pub_key[64] & 1 == 0 { 0x02 } else { 0x03 };
     compressed[1..33].copy_from_slice(&pub_key[1..33]);
     Ok(compressed)
 }
@@ -1,4 +1,5 @@
 mod error;
+pub mod events;
 mod execute;
 pub mod helpers;
 pub mod msg;
@@ -6,7 +7,7 @@ mod query;
 pub mod state;
 
 pub use crate::error::ContractError;
-pub use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+pub use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 pub use crate::state::MintWithClaimContract;
 
 pub const CONTRACT_NAME: &str = "MERCLE_MINT_WITH_CLAIM";
@@ -16,8 +17,10 @@ pub mod entry {
     use super::*;
 
     use cosmwasm_std::{
-        entry_point, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult,
+        entry_point, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Reply, Response, StdError,
+        StdResult,
     };
+    use semver::Version;
 
     #[entry_point]
     pub fn instantiate(
@@ -43,6 +46,36 @@ pub mod entry {
         tract.execute(deps, env, info, msg)
     }
 
+    #[entry_point]
+    pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+        let tract = MintWithClaimContract::<Empty>::default();
+        tract.reply(deps, env, msg)
+    }
+
+    // Upgrades an older MintWithClaim. `digest_version` is left unset on contracts deployed before
+    // it, so they keep verifying claims with the `Legacy` digest until an admin switches to `V1`.
+    #[entry_point]
+    pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+        let stored = cw2::get_contract_version(deps.storage)?;
+        let cannot_migrate = || ContractError::CannotMigrate {
+            contract: stored.contract.to_owned(),
+            version: stored.version.to_owned(),
+        };
+        let version: Version = stored.version.parse().map_err(|_| cannot_migrate())?;
+        let new_version: Version = CONTRACT_VERSION
+            .parse()
+            .map_err(|_| StdError::generic_err("Invalid contract version"))?;
+        if stored.contract != CONTRACT_NAME || version >= new_version {
+            return Err(cannot_migrate());
+        }
+
+        cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+        Ok(Response::new()
+            .add_attribute("action", "migrate")
+            .add_attribute("from_version", stored.version)
+            .add_attribute("to_version", CONTRACT_VERSION))
+    }
+
     #[entry_point]
     pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         let tract = MintWithClaimContract::<Empty>::default();
@@ -1,6 +1,10 @@
-use crate::state::Role;
+use crate::state::{
+    Campaign, CampaignConfig, ClaimRecord, DigestVersion, FeeShare, IssuerKey, KeyType,
+    MembershipContract, MintFailureMode, ReferralStats, Role,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Binary, Coin};
+use cosmwasm_std::{Addr, Binary, Coin, Timestamp, Uint128};
+use cw20::{Cw20CoinVerified, Cw20ReceiveMsg};
 
 #[cw_serde]
 pub enum MemberhsipExecute<T> {
@@ -27,6 +31,25 @@ pub struct MembershipMintMsg<T> {
 #[cw_serde]
 pub struct InstantiateMsg {
     pub treasury: Addr,
+    /// Maximum allowed span, in seconds, between a claim's `issued_at` and `expires_at`.
+    pub max_claim_validity: Option<u64>,
+    /// Signature digest scheme, defaults to `V1`.
+    pub digest_version: Option<DigestVersion>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub enum SignMode {
+    /// Raw secp256k1 signature over the claim digest, signer recovered with `recovery_byte`.
+    Secp256k1,
+    /// ADR-036 `signArbitrary` signature whose data is the claim digest.
+    Adr036 { pubkey: Binary },
+    /// Ethereum `personal_sign` over the claim digest, recovered address must be a registered issuer key.
+    Eip191,
+    /// ed25519 signature over the claim digest by a registered issuer key.
+    Ed25519 { pubkey: Binary },
 }
 
 #[cw_serde]
@@ -36,16 +59,143 @@ pub struct Message {
     pub token_uri: String,
     pub fee: Coin,
     pub verifying_contract: Addr,
+    pub claim_contract: Addr,
     pub chain_id: String,
     pub bech32_hre: String,
-    pub timestamp: String,
+    pub issued_at: Timestamp,
+    pub expires_at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Lets any sender submit the claim on behalf of `to`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayable: Option<bool>,
+    /// Campaign whose limits the claim counts against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<String>,
+    /// JSON object forwarded byte for byte as the `extension` of the membership `Mint`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<Binary>,
+    /// Fee payable in a cw20 token through `Receive` instead of `fee`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cw20_fee: Option<Cw20CoinVerified>,
+    /// Receives the referral share of the fee.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<Addr>,
+}
+
+/// Message embedded in a cw20 `Send` to MintWithClaim.
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
+pub enum Cw20HookMsg {
+    /// Mints with a claim whose `cw20_fee` matches the sent token and amount.
+    MintWithClaim {
+        message: Message,
+        signature: Binary,
+        recovery_byte: u8,
+        sign_mode: Option<SignMode>,
+        consent: Option<RecipientConsent>,
+        co_signatures: Option<Vec<IssuerSignature>>,
+    },
+}
+
+/// Signature by the recipient over the consent digest of a claim, allowing a relayer to submit it.
+#[cw_serde]
+pub struct RecipientConsent {
+    /// Compressed or uncompressed secp256k1 key that derives to `to`.
+    pub pubkey: Binary,
+    pub signature: Binary,
+    /// Signature is an ADR-036 `signArbitrary` over the consent digest instead of a raw one.
+    #[serde(default)]
+    pub adr036: bool,
+}
+
+/// Additional issuer signature over the same claim digest, counted towards the issuer threshold.
+#[cw_serde]
+pub struct IssuerSignature {
+    pub signature: Binary,
+    pub recovery_byte: u8,
+    pub sign_mode: Option<SignMode>,
 }
 
 #[cw_serde]
+pub struct Claim {
+    pub message: Message,
+    pub signature: Binary,
+    pub recovery_byte: u8,
+    pub sign_mode: Option<SignMode>,
+    pub consent: Option<RecipientConsent>,
+    pub co_signatures: Option<Vec<IssuerSignature>>,
+}
+
+impl Claim {
+    pub fn issuer_signature(&self) -> IssuerSignature {
+        IssuerSignature {
+            signature: self.signature.to_owned(),
+            recovery_byte: self.recovery_byte,
+            sign_mode: self.sign_mode.to_owned(),
+        }
+    }
+}
+
+/// Identifies an unused claim to cancel.
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
+pub enum ClaimRevocation {
+    /// Revokes the claim with this message, callable by its issuer (`from`) or an admin.
+    Message { message: Message },
+    /// Revokes any claim by `issuer` carrying `nonce`, callable by the issuer or an admin.
+    Nonce { issuer: Addr, nonce: u64 },
+    /// Revokes the claim with this digest, admin only.
+    Digest { digest: Binary },
+}
+
+#[cw_serde]
+pub enum BatchMode {
+    /// Any invalid claim fails the whole batch.
+    AllOrNothing,
+    /// Invalid claims are skipped and reported in the response attributes.
+    SkipInvalid,
+}
+
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     SetTreasury {
         address: Addr,
     },
+    /// Splits fees between weighted recipients instead of the treasury, `None` removes it.
+    SetFeeSplit {
+        split: Option<Vec<FeeShare>>,
+    },
+    /// Basis points of each fee paid to the claim's referrer, `None` stops referral rewards.
+    SetReferralShare {
+        bps: Option<u16>,
+    },
+    /// Keeps native and cw20 fees in the contract instead of sending them to the fee split.
+    SetVaultMode {
+        enabled: bool,
+    },
+    /// Pays out fees held in the vault.
+    Withdraw {
+        denom: String,
+        amount: Uint128,
+        recipient: Addr,
+    },
+    /// Pays out cw20 fees of `token` held in the vault.
+    WithdrawCw20 {
+        token: Addr,
+        amount: Uint128,
+        recipient: Addr,
+    },
+    SetMaxClaimValidity {
+        seconds: Option<u64>,
+    },
+    SetDigestVersion {
+        version: DigestVersion,
+    },
+    SetMintFailureMode {
+        mode: MintFailureMode,
+    },
     GrantRole {
         role: Role,
         address: Addr,
@@ -54,28 +204,214 @@ pub enum ExecuteMsg {
         role: Role,
         address: Addr,
     },
+    RegisterIssuerKey {
+        key_type: KeyType,
+        key: Binary,
+        issuer: Addr,
+        label: String,
+        scope: Option<Vec<Addr>>,
+        valid_from: Option<Timestamp>,
+        valid_until: Option<Timestamp>,
+    },
+    RevokeIssuerKey {
+        key: Binary,
+    },
+    RotateIssuerKey {
+        key: Binary,
+        new_key_type: KeyType,
+        new_key: Binary,
+    },
+    RemoveIssuerKey {
+        key: Binary,
+    },
     MintWithClaim {
         message: Message,
         signature: Binary,
         recovery_byte: u8,
+        sign_mode: Option<SignMode>,
+        consent: Option<RecipientConsent>,
+        co_signatures: Option<Vec<IssuerSignature>>,
+    },
+    /// Redeems a claim with a cw20 fee, the embedded message is a `Cw20HookMsg`.
+    Receive(Cw20ReceiveMsg),
+    /// Sets the allowlist root for a membership contract and campaign, `None` removes it.
+    SetMerkleRoot {
+        verifying_contract: Addr,
+        campaign: String,
+        root: Option<Binary>,
+    },
+    /// Mints to the sender when `(sender, token_uri, fee)` is a leaf of the campaign's root. When
+    /// `campaign` is also the id of a campaign, the mint counts against its time window, supply
+    /// and per-address limits and its fee goes to the campaign treasury. Like signed claims, the
+    /// fee is paid out once the mint succeeds and refunded with the leaf in refund mode.
+    MintWithMerkleProof {
+        verifying_contract: Addr,
+        campaign: String,
+        token_uri: String,
+        fee: Coin,
+        proof: Vec<Binary>,
+    },
+    /// Adds or updates a membership contract claims may target.
+    RegisterMembershipContract {
+        contract: Addr,
+        config: MembershipContract,
+    },
+    RemoveMembershipContract {
+        contract: Addr,
+    },
+    CreateCampaign {
+        id: String,
+        config: CampaignConfig,
+    },
+    /// Replaces the campaign config, counters are kept.
+    UpdateCampaign {
+        id: String,
+        config: CampaignConfig,
+    },
+    CloseCampaign {
+        id: String,
+    },
+    RevokeClaim {
+        revocation: ClaimRevocation,
+    },
+    RevokeClaims {
+        revocations: Vec<ClaimRevocation>,
+    },
+    MintWithClaimBatch {
+        claims: Vec<Claim>,
+        /// Defaults to `AllOrNothing`.
+        mode: Option<BatchMode>,
     },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
+#[allow(clippy::large_enum_variant)]
 pub enum QueryMsg {
     #[returns(VerifyClaimResponse)]
     VerifySign {
         message: Message,
         signature: Binary,
         recovery_byte: u8,
+        sign_mode: Option<SignMode>,
+        co_signatures: Option<Vec<IssuerSignature>>,
+    },
+
+    /// Dry runs `MintWithClaim` for `sender` attaching `funds`, reporting every check.
+    #[returns(VerifyClaimDetailedResponse)]
+    VerifyClaimDetailed {
+        claim: Claim,
+        sender: Addr,
+        funds: Vec<Coin>,
     },
 
     #[returns(TreasuryResponse)]
     GetTreasury {},
 
+    /// Recipients of fees for claims on the given membership contract and campaign.
+    #[returns(FeeSplitResponse)]
+    GetFeeSplit {
+        verifying_contract: Option<Addr>,
+        campaign_id: Option<String>,
+    },
+
+    /// Vault balances and lifetime fee totals, overall and for `verifying_contract`.
+    #[returns(RevenueResponse)]
+    Revenue { verifying_contract: Option<Addr> },
+
+    #[returns(ReferralShareResponse)]
+    GetReferralShare {},
+
+    #[returns(ReferrerResponse)]
+    GetReferrer { address: Addr },
+
+    #[returns(ReferrersResponse)]
+    ListReferrers {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+
+    #[returns(MaxClaimValidityResponse)]
+    GetMaxClaimValidity {},
+
+    #[returns(DigestVersionResponse)]
+    GetDigestVersion {},
+
+    #[returns(MintFailureModeResponse)]
+    GetMintFailureMode {},
+
+    #[returns(ClaimDigestResponse)]
+    GetClaimDigest { message: Message },
+
     #[returns(HasRoleResponse)]
     HasRole { address: Addr, role: Role },
+
+    #[returns(IssuerKeyResponse)]
+    GetIssuerKey { key: Binary },
+
+    #[returns(IssuerKeysResponse)]
+    ListIssuerKeys {
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+
+    #[returns(IssuerKeysResponse)]
+    ListIssuerKeysByIssuer {
+        issuer: Addr,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+
+    #[returns(MembershipContractResponse)]
+    GetMembershipContract { contract: Addr },
+
+    #[returns(MembershipContractsResponse)]
+    ListMembershipContracts {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+
+    #[returns(CampaignResponse)]
+    GetCampaign { id: String },
+
+    #[returns(CampaignsResponse)]
+    ListCampaigns {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(CampaignClaimsResponse)]
+    GetCampaignClaims { id: String, address: Addr },
+
+    #[returns(IsClaimedResponse)]
+    IsClaimed { message: Message },
+
+    #[returns(ClaimsResponse)]
+    ClaimsByRecipient {
+        recipient: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(ClaimsResponse)]
+    ClaimsByContract {
+        contract: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(MerkleRootResponse)]
+    GetMerkleRoot {
+        verifying_contract: Addr,
+        campaign: String,
+    },
+
+    #[returns(IsLeafClaimedResponse)]
+    IsLeafClaimed {
+        verifying_contract: Addr,
+        campaign: String,
+        leaf: Binary,
+    },
 }
 
 #[cw_serde]
@@ -83,6 +419,29 @@ pub struct VerifyClaimResponse {
     pub value: bool,
 }
 
+#[cw_serde]
+pub struct VerifyClaimDetailedResponse {
+    /// Whether `MintWithClaim` would succeed.
+    pub valid: bool,
+    /// Error `MintWithClaim` would fail with.
+    pub error: Option<String>,
+    pub signer: Option<Addr>,
+    pub recovery_error: Option<String>,
+    pub signers: Vec<Addr>,
+    pub is_duplicate: bool,
+    pub is_revoked: bool,
+    pub is_sign_valid: bool,
+    pub has_role: bool,
+    pub is_receiver: bool,
+    pub is_fee_paid: bool,
+    /// Fee that would be charged.
+    pub fee: Option<Coin>,
+    pub is_within_window: bool,
+    pub is_domain_valid: bool,
+    pub is_contract_enabled: bool,
+    pub is_campaign_valid: bool,
+}
+
 #[cw_serde]
 pub struct HasRoleResponse {
     pub value: bool,
@@ -92,3 +451,110 @@ pub struct HasRoleResponse {
 pub struct TreasuryResponse {
     pub value: Option<Addr>,
 }
+
+#[cw_serde]
+pub struct FeeSplitResponse {
+    pub recipients: Vec<FeeShare>,
+}
+
+#[cw_serde]
+pub struct RevenueResponse {
+    pub vault_mode: bool,
+    pub balances: Vec<Coin>,
+    pub totals: Vec<Coin>,
+    pub contract_totals: Vec<Coin>,
+    pub cw20_balances: Vec<Cw20CoinVerified>,
+    pub cw20_totals: Vec<Cw20CoinVerified>,
+    pub cw20_contract_totals: Vec<Cw20CoinVerified>,
+}
+
+#[cw_serde]
+pub struct ReferralShareResponse {
+    pub value: Option<u16>,
+}
+
+#[cw_serde]
+pub struct ReferrerResponse {
+    pub value: Option<ReferralStats>,
+}
+
+#[cw_serde]
+pub struct ReferrersResponse {
+    pub referrers: Vec<(Addr, ReferralStats)>,
+}
+
+#[cw_serde]
+pub struct MaxClaimValidityResponse {
+    pub value: Option<u64>,
+}
+
+#[cw_serde]
+pub struct DigestVersionResponse {
+    pub value: DigestVersion,
+}
+
+#[cw_serde]
+pub struct MintFailureModeResponse {
+    pub value: MintFailureMode,
+}
+
+#[cw_serde]
+pub struct ClaimDigestResponse {
+    pub version: DigestVersion,
+    pub digest: Binary,
+}
+
+#[cw_serde]
+pub struct IssuerKeyResponse {
+    pub value: Option<IssuerKey>,
+}
+
+#[cw_serde]
+pub struct IssuerKeysResponse {
+    pub keys: Vec<(Binary, IssuerKey)>,
+}
+
+#[cw_serde]
+pub struct MerkleRootResponse {
+    pub value: Option<Binary>,
+}
+
+#[cw_serde]
+pub struct IsLeafClaimedResponse {
+    pub value: bool,
+}
+
+#[cw_serde]
+pub struct CampaignResponse {
+    pub value: Option<Campaign>,
+}
+
+#[cw_serde]
+pub struct CampaignsResponse {
+    pub campaigns: Vec<(String, Campaign)>,
+}
+
+#[cw_serde]
+pub struct CampaignClaimsResponse {
+    pub value: u64,
+}
+
+#[cw_serde]
+pub struct MembershipContractResponse {
+    pub value: Option<MembershipContract>,
+}
+
+#[cw_serde]
+pub struct MembershipContractsResponse {
+    pub contracts: Vec<(Addr, MembershipContract)>,
+}
+
+#[cw_serde]
+pub struct IsClaimedResponse {
+    pub value: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsResponse {
+    pub claims: Vec<(u64, ClaimRecord)>,
+}
@@ -1,43 +1,211 @@
-use crate::msg::{HasRoleResponse, Message, QueryMsg, TreasuryResponse, VerifyClaimResponse};
-use crate::state::{MintWithClaimContract, Role};
-use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, Env, StdResult};
+use crate::helpers::{check_fee_payment, message_digest};
+use crate::msg::{
+    CampaignClaimsResponse, CampaignResponse, CampaignsResponse, Claim, ClaimDigestResponse,
+    ClaimsResponse, DigestVersionResponse, FeeSplitResponse, HasRoleResponse, IsClaimedResponse,
+    IsLeafClaimedResponse, IssuerKeyResponse, IssuerKeysResponse, MaxClaimValidityResponse,
+    MembershipContractResponse, MembershipContractsResponse, MerkleRootResponse, Message,
+    MintFailureModeResponse, QueryMsg, ReferralShareResponse, ReferrerResponse, ReferrersResponse,
+    RevenueResponse, TreasuryResponse, VerifyClaimDetailedResponse, VerifyClaimResponse,
+};
+use crate::state::{
+    Campaign, ClaimRecord, IssuerKey, MembershipContract, MintWithClaimContract, ReferralStats,
+    Role,
+};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, Deps, Env, MessageInfo, Order, StdResult, Uint128,
+};
+use cw20::Cw20CoinVerified;
+use cw_storage_plus::Bound;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
 
 impl<'a, C> MintWithClaimContract<'a, C> {
-    pub fn query(&self, deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    pub fn query(&self, deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         match msg {
             QueryMsg::VerifySign {
                 message,
                 recovery_byte,
                 signature,
-            } => to_json_binary(&self.verify_claim(deps, message, signature, recovery_byte)?),
+                sign_mode,
+                co_signatures,
+            } => to_json_binary(&self.verify_claim(
+                deps,
+                env,
+                Claim {
+                    message,
+                    signature,
+                    recovery_byte,
+                    sign_mode,
+                    consent: None,
+                    co_signatures,
+                },
+            )?),
+            QueryMsg::VerifyClaimDetailed {
+                claim,
+                sender,
+                funds,
+            } => to_json_binary(&self.verify_claim_detailed(deps, env, claim, sender, funds)?),
             QueryMsg::GetTreasury {} => to_json_binary(&self.get_treasury(deps)?),
+            QueryMsg::GetFeeSplit {
+                verifying_contract,
+                campaign_id,
+            } => to_json_binary(&self.get_fee_split(deps, verifying_contract, campaign_id)?),
+            QueryMsg::Revenue { verifying_contract } => {
+                to_json_binary(&self.revenue(deps, verifying_contract)?)
+            }
+            QueryMsg::GetReferralShare {} => to_json_binary(&self.get_referral_share(deps)?),
+            QueryMsg::GetReferrer { address } => to_json_binary(&self.get_referrer(deps, address)?),
+            QueryMsg::ListReferrers { start_after, limit } => {
+                to_json_binary(&self.list_referrers(deps, start_after, limit)?)
+            }
+            QueryMsg::GetMaxClaimValidity {} => to_json_binary(&self.get_max_claim_validity(deps)?),
+            QueryMsg::GetDigestVersion {} => to_json_binary(&self.digest_version_info(deps)?),
+            QueryMsg::GetMintFailureMode {} => to_json_binary(&self.mint_failure_mode_info(deps)?),
+            QueryMsg::GetClaimDigest { message } => {
+                to_json_binary(&self.claim_digest_info(deps, message)?)
+            }
             QueryMsg::HasRole { address, role } => {
                 to_json_binary(&self.address_has_role(deps, address, role)?)
             }
+            QueryMsg::GetIssuerKey { key } => to_json_binary(&self.get_issuer_key(deps, key)?),
+            QueryMsg::ListIssuerKeys { start_after, limit } => {
+                to_json_binary(&self.list_issuer_keys(deps, start_after, limit)?)
+            }
+            QueryMsg::ListIssuerKeysByIssuer {
+                issuer,
+                start_after,
+                limit,
+            } => to_json_binary(&self.list_issuer_keys_by_issuer(
+                deps,
+                issuer,
+                start_after,
+                limit,
+            )?),
+            QueryMsg::GetMembershipContract { contract } => {
+                to_json_binary(&self.get_membership_contract(deps, contract)?)
+            }
+            QueryMsg::ListMembershipContracts { start_after, limit } => {
+                to_json_binary(&self.list_membership_contracts(deps, start_after, limit)?)
+            }
+            QueryMsg::GetCampaign { id } => to_json_binary(&self.get_campaign(deps, id)?),
+            QueryMsg::ListCampaigns { start_after, limit } => {
+                to_json_binary(&self.list_campaigns(deps, start_after, limit)?)
+            }
+            QueryMsg::GetCampaignClaims { id, address } => {
+                to_json_binary(&self.get_campaign_claims(deps, id, address)?)
+            }
+            QueryMsg::IsClaimed { message } => to_json_binary(&self.is_claimed(deps, message)?),
+            QueryMsg::ClaimsByRecipient {
+                recipient,
+                start_after,
+                limit,
+            } => to_json_binary(&self.claims_by_recipient(deps, recipient, start_after, limit)?),
+            QueryMsg::ClaimsByContract {
+                contract,
+                start_after,
+                limit,
+            } => to_json_binary(&self.claims_by_contract(deps, contract, start_after, limit)?),
+            QueryMsg::GetMerkleRoot {
+                verifying_contract,
+                campaign,
+            } => to_json_binary(&self.get_merkle_root(deps, verifying_contract, campaign)?),
+            QueryMsg::IsLeafClaimed {
+                verifying_contract,
+                campaign,
+                leaf,
+            } => to_json_binary(&self.is_leaf_claimed(deps, verifying_contract, campaign, leaf)?),
         }
     }
 }
 
 impl<'a, C> MintWithClaimContract<'a, C> {
-    fn verify_claim(
+    fn verify_claim(&self, deps: Deps, env: Env, claim: Claim) -> StdResult<VerifyClaimResponse> {
+        let value = self
+            .validate_claim(deps, &env, &claim)
+            .unwrap_or_default()
+            .is_valid();
+
+        Ok(VerifyClaimResponse { value })
+    }
+
+    fn verify_claim_detailed(
         &self,
         deps: Deps,
-        message: Message,
-        signature: Binary,
-        recovery_byte: u8,
-    ) -> StdResult<VerifyClaimResponse> {
-        let (is_duplicate, is_sign_valid, has_role) = self
-            .validate_claim(
-                deps,
-                message.to_owned(),
-                signature.to_owned(),
-                recovery_byte,
+        env: Env,
+        claim: Claim,
+        sender: Addr,
+        funds: Vec<Coin>,
+    ) -> StdResult<VerifyClaimDetailedResponse> {
+        let message = &claim.message;
+
+        let is_domain_valid = self.check_claim_domain(&env, message).is_ok();
+        let is_contract_enabled = self
+            .check_membership_contract(deps.storage, &message.verifying_contract)
+            .is_ok();
+        let is_within_window = self.check_claim_window(deps.storage, &env, message).is_ok();
+        let is_campaign_valid = self.check_campaign(deps.storage, &env, message).is_ok();
+        let is_duplicate = self.is_claim_used(deps.storage, &claim)?;
+        let is_revoked = self.is_claim_revoked(deps.storage, message)?;
+
+        let hash = message_digest(message, self.get_digest_version(deps.storage)?)?;
+        let (signer, recovery_error, is_sign_valid, has_role) =
+            match self.claim_signer(deps, &env, &claim, &hash) {
+                Ok((signer, is_verified)) => {
+                    let has_role = self
+                        .is_claim_issuer(deps, &message.verifying_contract, &signer)
+                        .unwrap_or_default();
+                    let is_sign_valid = is_verified && message.from == signer;
+                    (Some(signer), None, is_sign_valid, has_role)
+                }
+                Err(err) => (None, Some(err.to_string()), false, false),
+            };
+
+        let is_receiver = self.check_recipient(deps, &sender, &claim).is_ok();
+
+        let info = MessageInfo { sender, funds };
+        let fee = self
+            .fee_payout(
+                deps.storage,
+                &message.verifying_contract,
+                message.campaign_id.as_deref(),
+                &message.fee,
             )
-            .unwrap_or_default();
+            .ok()
+            .map(|(_, fee)| fee);
+        // cw20 fees can only be paid through `Receive`
+        let is_fee_paid = message.cw20_fee.is_none()
+            && fee
+                .as_ref()
+                .is_some_and(|fee| check_fee_payment(&info, fee).is_ok());
 
-        let value = !is_duplicate && is_sign_valid && has_role;
+        // The same checks `MintWithClaim` runs before changing any state
+        let mut signers = vec![];
+        let result = self
+            .check_claim(deps, &env, &info.sender, &claim, None)
+            .and_then(|claim_signers| {
+                signers = claim_signers;
+                self.check_claim_fee(deps.storage, &info, message)
+            });
 
-        Ok(VerifyClaimResponse { value })
+        Ok(VerifyClaimDetailedResponse {
+            valid: result.is_ok(),
+            error: result.err().map(|err| err.to_string()),
+            signer,
+            recovery_error,
+            signers,
+            is_duplicate,
+            is_revoked,
+            is_sign_valid,
+            has_role,
+            is_receiver,
+            is_fee_paid,
+            fee,
+            is_within_window,
+            is_domain_valid,
+            is_contract_enabled,
+            is_campaign_valid,
+        })
     }
 
     fn get_treasury(&self, deps: Deps) -> StdResult<TreasuryResponse> {
@@ -45,6 +213,133 @@ impl<'a, C> MintWithClaimContract<'a, C> {
         Ok(TreasuryResponse { value })
     }
 
+    fn get_fee_split(
+        &self,
+        deps: Deps,
+        verifying_contract: Option<Addr>,
+        campaign_id: Option<String>,
+    ) -> StdResult<FeeSplitResponse> {
+        let recipients = self.active_fee_split(
+            deps.storage,
+            verifying_contract.as_ref(),
+            campaign_id.as_deref(),
+        )?;
+        Ok(FeeSplitResponse { recipients })
+    }
+
+    fn revenue(&self, deps: Deps, verifying_contract: Option<Addr>) -> StdResult<RevenueResponse> {
+        let coins = |entries: Vec<(String, Uint128)>| -> Vec<Coin> {
+            entries
+                .into_iter()
+                .map(|(denom, amount)| Coin { denom, amount })
+                .collect()
+        };
+
+        let balances = self
+            .vault_balances
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        let totals = self
+            .fee_totals
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        let contract_totals = match &verifying_contract {
+            Some(contract) => self
+                .contract_revenue
+                .prefix(contract)
+                .range(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()?,
+            None => vec![],
+        };
+
+        let cw20_coins = |entries: Vec<(Addr, Uint128)>| -> Vec<Cw20CoinVerified> {
+            entries
+                .into_iter()
+                .map(|(address, amount)| Cw20CoinVerified { address, amount })
+                .collect()
+        };
+        let cw20_balances = self
+            .cw20_vault_balances
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        let cw20_totals = self
+            .cw20_fee_totals
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        let cw20_contract_totals = match &verifying_contract {
+            Some(contract) => self
+                .cw20_contract_revenue
+                .prefix(contract)
+                .range(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()?,
+            None => vec![],
+        };
+
+        Ok(RevenueResponse {
+            vault_mode: self.is_vault_mode(deps.storage)?,
+            balances: coins(balances),
+            totals: coins(totals),
+            contract_totals: coins(contract_totals),
+            cw20_balances: cw20_coins(cw20_balances),
+            cw20_totals: cw20_coins(cw20_totals),
+            cw20_contract_totals: cw20_coins(cw20_contract_totals),
+        })
+    }
+
+    fn get_referral_share(&self, deps: Deps) -> StdResult<ReferralShareResponse> {
+        let value = self.referral_bps.may_load(deps.storage)?;
+        Ok(ReferralShareResponse { value })
+    }
+
+    fn get_referrer(&self, deps: Deps, address: Addr) -> StdResult<ReferrerResponse> {
+        let value = self.referrals.may_load(deps.storage, &address)?;
+        Ok(ReferrerResponse { value })
+    }
+
+    fn list_referrers(
+        &self,
+        deps: Deps,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<ReferrersResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.as_ref().map(Bound::exclusive);
+
+        let referrers: StdResult<Vec<(Addr, ReferralStats)>> = self
+            .referrals
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect();
+
+        Ok(ReferrersResponse {
+            referrers: referrers?,
+        })
+    }
+
+    fn mint_failure_mode_info(&self, deps: Deps) -> StdResult<MintFailureModeResponse> {
+        let value = self.get_mint_failure_mode(deps.storage)?;
+        Ok(MintFailureModeResponse { value })
+    }
+
+    fn get_max_claim_validity(&self, deps: Deps) -> StdResult<MaxClaimValidityResponse> {
+        let value = self.max_claim_validity.may_load(deps.storage)?;
+        Ok(MaxClaimValidityResponse { value })
+    }
+
+    fn digest_version_info(&self, deps: Deps) -> StdResult<DigestVersionResponse> {
+        let value = self.get_digest_version(deps.storage)?;
+        Ok(DigestVersionResponse { value })
+    }
+
+    fn claim_digest_info(&self, deps: Deps, message: Message) -> StdResult<ClaimDigestResponse> {
+        let version = self.get_digest_version(deps.storage)?;
+        let digest = message_digest(&message, version)?;
+        Ok(ClaimDigestResponse {
+            version,
+            digest: Binary::from(digest),
+        })
+    }
+
     fn address_has_role(
         &self,
         deps: Deps,
@@ -54,4 +349,195 @@ impl<'a, C> MintWithClaimContract<'a, C> {
         let value = self.has_role(deps.storage, &address, role)?;
         Ok(HasRoleResponse { value })
     }
+
+    fn get_issuer_key(&self, deps: Deps, key: Binary) -> StdResult<IssuerKeyResponse> {
+        let value = self.issuer_keys.may_load(deps.storage, &key)?;
+        Ok(IssuerKeyResponse { value })
+    }
+
+    fn list_issuer_keys(
+        &self,
+        deps: Deps,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    ) -> StdResult<IssuerKeysResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+        let keys: StdResult<Vec<(Binary, IssuerKey)>> = self
+            .issuer_keys
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(k, v)| (Binary::from(k), v)))
+            .collect();
+
+        Ok(IssuerKeysResponse { keys: keys? })
+    }
+
+    fn list_issuer_keys_by_issuer(
+        &self,
+        deps: Deps,
+        issuer: Addr,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    ) -> StdResult<IssuerKeysResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+        let keys: StdResult<Vec<(Binary, IssuerKey)>> = self
+            .issuer_keys
+            .idx
+            .issuer
+            .prefix(issuer)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(k, v)| (Binary::from(k), v)))
+            .collect();
+
+        Ok(IssuerKeysResponse { keys: keys? })
+    }
+
+    fn get_membership_contract(
+        &self,
+        deps: Deps,
+        contract: Addr,
+    ) -> StdResult<MembershipContractResponse> {
+        let value = self
+            .membership_contracts
+            .may_load(deps.storage, &contract)?;
+        Ok(MembershipContractResponse { value })
+    }
+
+    fn list_membership_contracts(
+        &self,
+        deps: Deps,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<MembershipContractsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.as_ref().map(Bound::exclusive);
+
+        let contracts: StdResult<Vec<(Addr, MembershipContract)>> = self
+            .membership_contracts
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect();
+
+        Ok(MembershipContractsResponse {
+            contracts: contracts?,
+        })
+    }
+
+    fn get_campaign(&self, deps: Deps, id: String) -> StdResult<CampaignResponse> {
+        let value = self.campaigns.may_load(deps.storage, &id)?;
+        Ok(CampaignResponse { value })
+    }
+
+    fn list_campaigns(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CampaignsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.as_deref().map(Bound::exclusive);
+
+        let campaigns: StdResult<Vec<(String, Campaign)>> = self
+            .campaigns
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect();
+
+        Ok(CampaignsResponse {
+            campaigns: campaigns?,
+        })
+    }
+
+    fn get_campaign_claims(
+        &self,
+        deps: Deps,
+        id: String,
+        address: Addr,
+    ) -> StdResult<CampaignClaimsResponse> {
+        let value = self
+            .campaign_claims
+            .may_load(deps.storage, (&id, &address))?
+            .unwrap_or_default();
+        Ok(CampaignClaimsResponse { value })
+    }
+
+    fn is_claimed(&self, deps: Deps, message: Message) -> StdResult<IsClaimedResponse> {
+        let value = self.is_message_used(deps.storage, &message)?;
+        Ok(IsClaimedResponse { value })
+    }
+
+    fn claims_by_recipient(
+        &self,
+        deps: Deps,
+        recipient: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ClaimsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let claims: StdResult<Vec<(u64, ClaimRecord)>> = self
+            .claim_records
+            .idx
+            .recipient
+            .prefix(recipient)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect();
+
+        Ok(ClaimsResponse { claims: claims? })
+    }
+
+    fn claims_by_contract(
+        &self,
+        deps: Deps,
+        contract: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ClaimsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let claims: StdResult<Vec<(u64, ClaimRecord)>> = self
+            .claim_records
+            .idx
+            .verifying_contract
+            .prefix(contract)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect();
+
+        Ok(ClaimsResponse { claims: claims? })
+    }
+
+    fn get_merkle_root(
+        &self,
+        deps: Deps,
+        verifying_contract: Addr,
+        campaign: String,
+    ) -> StdResult<MerkleRootResponse> {
+        let value = self
+            .merkle_roots
+            .may_load(deps.storage, (&verifying_contract, &campaign))?;
+        Ok(MerkleRootResponse { value })
+    }
+
+    fn is_leaf_claimed(
+        &self,
+        deps: Deps,
+        verifying_contract: Addr,
+        campaign: String,
+        leaf: Binary,
+    ) -> StdResult<IsLeafClaimedResponse> {
+        let value = self
+            .used_leaves
+            .may_load(deps.storage, (&verifying_contract, &campaign, &leaf))?
+            .unwrap_or_default();
+        Ok(IsLeafClaimedResponse { value })
+    }
 }
@@ -1,15 +1,27 @@
 use crate::{
-    helpers::{get_key_for_role, recover_signer},
-    msg::{HasRoleResponse, MemberhsipQuery, Message},
+    error::ContractError,
+    helpers::{
+        check_fee_payment, consent_digest, eip191_hash, get_key_for_role, is_low_s_signature,
+        message_digest, normalize_pubkey, parse_extension, pubkey_to_address, recover_eth_address,
+        recover_pubkey, verify_adr036_signature,
+    },
+    msg::{Claim, HasRoleResponse, IssuerSignature, MemberhsipQuery, Message, SignMode},
 };
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Deps, QueryRequest, StdResult, Storage, WasmQuery,
+    to_json_binary, Addr, Binary, Coin, Deps, Env, MessageInfo, QueryRequest, StdError, StdResult,
+    Storage, Timestamp, Uint128, WasmQuery,
 };
-use cw_storage_plus::{Item, Map};
+use cw20::Cw20CoinVerified;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
+// Tolerated drift, in seconds, between the issuer's clock and block time.
+const CLAIM_CLOCK_SKEW: u64 = 60;
+// Fee split weights are basis points of the fee
+pub const FULL_BPS: u16 = 10_000;
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum Role {
     DefaultAdmin,
@@ -18,26 +30,381 @@ pub enum Role {
     Blacklisted,
 }
 
+/// How claims are hashed before signing.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum DigestVersion {
+    /// sha256 over the serde_json encoding of the `Message`, see `legacy_claim_digest`.
+    Legacy,
+    /// Domain separated typed digest, see `claim_digest`.
+    V1,
+}
+
+/// What happens to a claim when its membership mint fails. wasmd redacts the error a failed
+/// submessage passes to `reply` down to its code, e.g. `codespace: wasm, code: 5`, so only a
+/// mint that is not replied to can surface the membership contract's own error.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum MintFailureMode {
+    /// Reverts the transaction with the membership contract's error.
+    Revert,
+    /// Refunds the fee and leaves the claim unused, reporting the redacted error.
+    Refund,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum KeyType {
+    Secp256k1,
+    Eth,
+    Ed25519,
+}
+
+impl KeyType {
+    pub fn key_len(&self) -> usize {
+        match self {
+            KeyType::Secp256k1 => 33,
+            KeyType::Eth => 20,
+            KeyType::Ed25519 => 32,
+        }
+    }
+}
+
+// Outcome of checking a claim, `signers` lists the distinct issuers that signed it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClaimValidation {
+    pub is_duplicate: bool,
+    pub is_revoked: bool,
+    pub is_sign_valid: bool,
+    pub has_role: bool,
+    pub signers: Vec<Addr>,
+}
+
+impl ClaimValidation {
+    pub fn is_valid(&self) -> bool {
+        !self.is_duplicate && !self.is_revoked && self.is_sign_valid && self.has_role
+    }
+}
+
+/// Share of each claim fee paid to `address`, in basis points of the fee.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct FeeShare {
+    pub address: Addr,
+    pub bps: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct MembershipContract {
+    pub enabled: bool,
+    /// Charged for claims whose signed fee is zero.
+    pub default_fee: Option<Coin>,
+    /// Receives this contract's fees instead of the contract treasury.
+    pub treasury: Option<Addr>,
+    /// Splits this contract's fees, takes precedence over `treasury`.
+    pub fee_split: Option<Vec<FeeShare>>,
+    /// Distinct issuers that must sign each claim, defaults to one.
+    pub issuer_threshold: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct CampaignConfig {
+    pub verifying_contract: Addr,
+    pub start_time: Option<Timestamp>,
+    pub end_time: Option<Timestamp>,
+    pub max_claims: Option<u64>,
+    pub max_claims_per_address: Option<u64>,
+    /// Fee every claim of the campaign must sign for.
+    pub fee: Option<Coin>,
+    /// Receives the campaign's fees instead of the contract treasury.
+    pub treasury: Option<Addr>,
+    /// Overrides the membership contract's issuer threshold.
+    pub issuer_threshold: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Campaign {
+    pub config: CampaignConfig,
+    pub closed: bool,
+    pub claimed: u64,
+}
+
+/// Claim whose membership mint is in flight, settled when the mint replies.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PendingMint {
+    /// Pays the fee and receives it back if the mint is refunded.
+    pub sender: Addr,
+    pub source: MintSource,
+    pub split: Vec<FeeShare>,
+    pub fee: Coin,
+}
+
+/// What a pending mint redeems, freed again if the mint is refunded.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum MintSource {
+    Claim(Box<Message>),
+    /// Allowlist leaf of the sender, `campaign_id` is set when it counts against a campaign.
+    MerkleLeaf {
+        verifying_contract: Addr,
+        campaign: String,
+        leaf: Binary,
+        campaign_id: Option<String>,
+    },
+}
+
+impl MintSource {
+    pub fn verifying_contract(&self) -> &Addr {
+        match self {
+            MintSource::Claim(message) => &message.verifying_contract,
+            MintSource::MerkleLeaf {
+                verifying_contract, ..
+            } => verifying_contract,
+        }
+    }
+
+    pub fn referrer(&self) -> Option<&Addr> {
+        match self {
+            MintSource::Claim(message) => message.referrer.as_ref(),
+            MintSource::MerkleLeaf { .. } => None,
+        }
+    }
+
+    pub fn cw20_fee(&self) -> Option<&Cw20CoinVerified> {
+        match self {
+            MintSource::Claim(message) => message.cw20_fee.as_ref(),
+            MintSource::MerkleLeaf { .. } => None,
+        }
+    }
+}
+
+/// Claims referred by an address and the fee shares paid to it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ReferralStats {
+    pub count: u64,
+    pub earnings: Vec<Coin>,
+    pub cw20_earnings: Vec<Cw20CoinVerified>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct IssuerKey {
+    pub key_type: KeyType,
+    pub issuer: Addr,
+    pub label: String,
+    // Membership contracts the key may sign for, `None` allows any
+    pub scope: Option<Vec<Addr>>,
+    pub valid_from: Option<Timestamp>,
+    pub valid_until: Option<Timestamp>,
+    pub revoked: bool,
+}
+
+pub struct IssuerKeyIndexes<'a> {
+    pub issuer: MultiIndex<'a, Addr, IssuerKey, Vec<u8>>,
+}
+
+impl<'a> IndexList<IssuerKey> for IssuerKeyIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<IssuerKey>> + '_> {
+        let v: Vec<&dyn Index<IssuerKey>> = vec![&self.issuer];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn issuer_key_issuer_idx(_pk: &[u8], d: &IssuerKey) -> Addr {
+    d.issuer.clone()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ClaimRecord {
+    pub recipient: Addr,
+    /// Signing issuer, `None` for Merkle allowlist mints.
+    pub issuer: Option<Addr>,
+    pub verifying_contract: Addr,
+    pub token_uri: String,
+    /// Fee charged, after membership contract defaults.
+    pub fee: Coin,
+    /// cw20 fee charged through `Receive`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cw20_fee: Option<Cw20CoinVerified>,
+    pub campaign_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<Addr>,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+pub struct ClaimRecordIndexes<'a> {
+    pub recipient: MultiIndex<'a, Addr, ClaimRecord, u64>,
+    pub verifying_contract: MultiIndex<'a, Addr, ClaimRecord, u64>,
+}
+
+impl<'a> IndexList<ClaimRecord> for ClaimRecordIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ClaimRecord>> + '_> {
+        let v: Vec<&dyn Index<ClaimRecord>> = vec![&self.recipient, &self.verifying_contract];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn claim_record_recipient_idx(_pk: &[u8], d: &ClaimRecord) -> Addr {
+    d.recipient.clone()
+}
+
+pub fn claim_record_contract_idx(_pk: &[u8], d: &ClaimRecord) -> Addr {
+    d.verifying_contract.clone()
+}
+
 pub struct MintWithClaimContract<'a, C> {
     pub treasury: Item<'a, Addr>,
+    pub fee_split: Item<'a, Vec<FeeShare>>,
+    pub referral_bps: Item<'a, u16>,
+    pub referrals: Map<'a, &'a Addr, ReferralStats>,
+    pub vault_mode: Item<'a, bool>,
+    pub vault_balances: Map<'a, &'a str, Uint128>,
+    pub fee_totals: Map<'a, &'a str, Uint128>,
+    pub contract_revenue: Map<'a, (&'a Addr, &'a str), Uint128>,
+    pub cw20_vault_balances: Map<'a, &'a Addr, Uint128>,
+    pub cw20_fee_totals: Map<'a, &'a Addr, Uint128>,
+    pub cw20_contract_revenue: Map<'a, (&'a Addr, &'a Addr), Uint128>,
+    pub mint_failure_mode: Item<'a, MintFailureMode>,
+    pub pending_mints: Map<'a, u64, PendingMint>,
     pub claim_map: Map<'a, &'a [u8], bool>,
     pub role_map: Map<'a, (&'a Addr, &'a str), bool>,
+    pub max_claim_validity: Item<'a, u64>,
+    pub digest_version: Item<'a, DigestVersion>,
+    pub issuer_keys: IndexedMap<'a, &'a [u8], IssuerKey, IssuerKeyIndexes<'a>>,
+    pub used_digests: Map<'a, &'a [u8], bool>,
+    pub used_nonces: Map<'a, (&'a Addr, u64), bool>,
+    pub merkle_roots: Map<'a, (&'a Addr, &'a str), Binary>,
+    pub used_leaves: Map<'a, (&'a Addr, &'a str, &'a [u8]), bool>,
+    pub campaigns: Map<'a, &'a str, Campaign>,
+    pub campaign_claims: Map<'a, (&'a str, &'a Addr), u64>,
+    pub membership_contracts: Map<'a, &'a Addr, MembershipContract>,
+    pub revoked_digests: Map<'a, &'a [u8], bool>,
+    pub revoked_nonces: Map<'a, (&'a Addr, u64), bool>,
+    pub claim_count: Item<'a, u64>,
+    pub claim_records: IndexedMap<'a, u64, ClaimRecord, ClaimRecordIndexes<'a>>,
 
     pub(crate) _custom_response: PhantomData<C>,
 }
 
 impl<C> Default for MintWithClaimContract<'static, C> {
     fn default() -> Self {
-        Self::new("treasury", "claim_map", "role_map")
+        Self::new(
+            "treasury",
+            "fee_split",
+            "referral_bps",
+            "referrals",
+            "vault_mode",
+            "vault_balances",
+            "fee_totals",
+            "contract_revenue",
+            "cw20_vault_balances",
+            "cw20_fee_totals",
+            "cw20_contract_revenue",
+            "mint_failure_mode",
+            "pending_mints",
+            "claim_map",
+            "role_map",
+            "max_claim_validity",
+            "digest_version",
+            "issuer_keys",
+            "issuer_keys__issuer",
+            "used_digests",
+            "used_nonces",
+            "merkle_roots",
+            "used_leaves",
+            "campaigns",
+            "campaign_claims",
+            "membership_contracts",
+            "revoked_digests",
+            "revoked_nonces",
+            "claim_count",
+            "claim_records",
+            "claim_records__recipient",
+            "claim_records__contract",
+        )
     }
 }
 
 impl<'a, C> MintWithClaimContract<'a, C> {
-    fn new(treasury_key: &'a str, claimed_map_key: &'a str, role_map_key: &'a str) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        treasury_key: &'a str,
+        fee_split_key: &'a str,
+        referral_bps_key: &'a str,
+        referrals_key: &'a str,
+        vault_mode_key: &'a str,
+        vault_balances_key: &'a str,
+        fee_totals_key: &'a str,
+        contract_revenue_key: &'a str,
+        cw20_vault_balances_key: &'a str,
+        cw20_fee_totals_key: &'a str,
+        cw20_contract_revenue_key: &'a str,
+        mint_failure_mode_key: &'a str,
+        pending_mints_key: &'a str,
+        claimed_map_key: &'a str,
+        role_map_key: &'a str,
+        max_claim_validity_key: &'a str,
+        digest_version_key: &'a str,
+        issuer_keys_key: &'a str,
+        issuer_keys_issuer_key: &'a str,
+        used_digests_key: &'a str,
+        used_nonces_key: &'a str,
+        merkle_roots_key: &'a str,
+        used_leaves_key: &'a str,
+        campaigns_key: &'a str,
+        campaign_claims_key: &'a str,
+        membership_contracts_key: &'a str,
+        revoked_digests_key: &'a str,
+        revoked_nonces_key: &'a str,
+        claim_count_key: &'a str,
+        claim_records_key: &'a str,
+        claim_records_recipient_key: &'a str,
+        claim_records_contract_key: &'a str,
+    ) -> Self {
+        let issuer_key_indexes = IssuerKeyIndexes {
+            issuer: MultiIndex::new(
+                issuer_key_issuer_idx,
+                issuer_keys_key,
+                issuer_keys_issuer_key,
+            ),
+        };
+        let claim_record_indexes = ClaimRecordIndexes {
+            recipient: MultiIndex::new(
+                claim_record_recipient_idx,
+                claim_records_key,
+                claim_records_recipient_key,
+            ),
+            verifying_contract: MultiIndex::new(
+                claim_record_contract_idx,
+                claim_records_key,
+                claim_records_contract_key,
+            ),
+        };
         Self {
             treasury: Item::new(treasury_key),
+            fee_split: Item::new(fee_split_key),
+            referral_bps: Item::new(referral_bps_key),
+            referrals: Map::new(referrals_key),
+            vault_mode: Item::new(vault_mode_key),
+            vault_balances: Map::new(vault_balances_key),
+            fee_totals: Map::new(fee_totals_key),
+            contract_revenue: Map::new(contract_revenue_key),
+            cw20_vault_balances: Map::new(cw20_vault_balances_key),
+            cw20_fee_totals: Map::new(cw20_fee_totals_key),
+            cw20_contract_revenue: Map::new(cw20_contract_revenue_key),
+            mint_failure_mode: Item::new(mint_failure_mode_key),
+            pending_mints: Map::new(pending_mints_key),
             claim_map: Map::new(claimed_map_key),
             role_map: Map::new(role_map_key),
+            max_claim_validity: Item::new(max_claim_validity_key),
+            digest_version: Item::new(digest_version_key),
+            issuer_keys: IndexedMap::new(issuer_keys_key, issuer_key_indexes),
+            used_digests: Map::new(used_digests_key),
+            used_nonces: Map::new(used_nonces_key),
+            merkle_roots: Map::new(merkle_roots_key),
+            used_leaves: Map::new(used_leaves_key),
+            campaigns: Map::new(campaigns_key),
+            campaign_claims: Map::new(campaign_claims_key),
+            membership_contracts: Map::new(membership_contracts_key),
+            revoked_digests: Map::new(revoked_digests_key),
+            revoked_nonces: Map::new(revoked_nonces_key),
+            claim_count: Item::new(claim_count_key),
+            claim_records: IndexedMap::new(claim_records_key, claim_record_indexes),
             _custom_response: PhantomData,
         }
     }
@@ -64,41 +431,817 @@ impl<'a, C> MintWithClaimContract<'a, C> {
         Ok(val)
     }
 
+    // Looks up the issuer a registered key signs for, enforcing revocation, validity window
+    // and scope. Unregistered keys resolve to `None`.
+    pub fn registered_issuer(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        key_type: KeyType,
+        key: &[u8],
+        verifying_contract: &Addr,
+    ) -> Result<Option<Addr>, ContractError> {
+        let issuer_key = match self.issuer_keys.may_load(storage, key)? {
+            Some(issuer_key) if issuer_key.key_type == key_type => issuer_key,
+            _ => return Ok(None),
+        };
+
+        if issuer_key.revoked {
+            return Err(ContractError::IssuerKeyRevoked {});
+        }
+
+        let now = env.block.time;
+        let not_started = matches!(issuer_key.valid_from, Some(from) if from > now);
+        let ended = matches!(issuer_key.valid_until, Some(until) if until <= now);
+        if not_started || ended {
+            return Err(ContractError::IssuerKeyInactive {});
+        }
+
+        if let Some(scope) = &issuer_key.scope {
+            if !scope.contains(verifying_contract) {
+                return Err(ContractError::IssuerKeyOutOfScope {
+                    contract: verifying_contract.to_string(),
+                });
+            }
+        }
+
+        Ok(Some(issuer_key.issuer))
+    }
+
+    pub fn is_claim_revoked(&self, storage: &dyn Storage, message: &Message) -> StdResult<bool> {
+        let digest = message_digest(message, self.get_digest_version(storage)?)?;
+
+        let nonce_revoked = match message.nonce {
+            Some(nonce) => self
+                .revoked_nonces
+                .may_load(storage, (&message.from, nonce))?
+                .unwrap_or_default(),
+            None => false,
+        };
+
+        Ok(nonce_revoked
+            || self
+                .revoked_digests
+                .may_load(storage, &digest)?
+                .unwrap_or_default())
+    }
+
+    // Signatures recorded in `claim_map` before digest based replay protection still count as used
+    pub fn is_claim_used(&self, storage: &dyn Storage, claim: &Claim) -> StdResult<bool> {
+        Ok(self.is_message_used(storage, &claim.message)?
+            || self
+                .claim_map
+                .may_load(storage, &claim.signature)?
+                .unwrap_or_default())
+    }
+
+    pub fn is_message_used(&self, storage: &dyn Storage, message: &Message) -> StdResult<bool> {
+        let digest = message_digest(message, self.get_digest_version(storage)?)?;
+
+        let nonce_used = match message.nonce {
+            Some(nonce) => self
+                .used_nonces
+                .may_load(storage, (&message.from, nonce))?
+                .unwrap_or_default(),
+            None => false,
+        };
+
+        Ok(nonce_used
+            || self
+                .used_digests
+                .may_load(storage, &digest)?
+                .unwrap_or_default())
+    }
+
+    pub fn record_claim(&self, storage: &mut dyn Storage, record: &ClaimRecord) -> StdResult<u64> {
+        let id = self.claim_count.may_load(storage)?.unwrap_or_default() + 1;
+        self.claim_count.save(storage, &id)?;
+        self.claim_records.save(storage, id, record)?;
+        Ok(id)
+    }
+
+    pub fn mark_claim_used(&self, storage: &mut dyn Storage, message: &Message) -> StdResult<()> {
+        let digest = message_digest(message, self.get_digest_version(storage)?)?;
+        self.used_digests.save(storage, &digest, &true)?;
+        if let Some(nonce) = message.nonce {
+            self.used_nonces
+                .save(storage, (&message.from, nonce), &true)?;
+        }
+        if let Some(id) = &message.campaign_id {
+            self.count_campaign_claim(storage, id, &message.to)?;
+        }
+        Ok(())
+    }
+
+    // Counts a claim by `recipient` against the supply and per-address limits of campaign `id`
+    pub fn count_campaign_claim(
+        &self,
+        storage: &mut dyn Storage,
+        id: &str,
+        recipient: &Addr,
+    ) -> StdResult<()> {
+        self.campaigns.update(storage, id, |campaign| {
+            let mut campaign =
+                campaign.ok_or_else(|| StdError::not_found(format!("Campaign {}", id)))?;
+            campaign.claimed += 1;
+            Ok::<_, StdError>(campaign)
+        })?;
+        self.campaign_claims
+            .update(storage, (id, recipient), |count| {
+                Ok::<_, StdError>(count.unwrap_or_default() + 1)
+            })?;
+        Ok(())
+    }
+
+    // Reverts `count_campaign_claim`
+    pub fn uncount_campaign_claim(
+        &self,
+        storage: &mut dyn Storage,
+        id: &str,
+        recipient: &Addr,
+    ) -> StdResult<()> {
+        self.campaigns.update(storage, id, |campaign| {
+            let mut campaign =
+                campaign.ok_or_else(|| StdError::not_found(format!("Campaign {}", id)))?;
+            campaign.claimed = campaign.claimed.saturating_sub(1);
+            Ok::<_, StdError>(campaign)
+        })?;
+        self.campaign_claims
+            .update(storage, (id, recipient), |count| {
+                Ok::<_, StdError>(count.unwrap_or_default().saturating_sub(1))
+            })?;
+        Ok(())
+    }
+
+    // Reverts `mark_claim_used` for a claim whose mint was refunded
+    pub fn unmark_claim_used(&self, storage: &mut dyn Storage, message: &Message) -> StdResult<()> {
+        let digest = message_digest(message, self.get_digest_version(storage)?)?;
+        self.used_digests.remove(storage, &digest);
+        if let Some(nonce) = message.nonce {
+            self.used_nonces.remove(storage, (&message.from, nonce));
+        }
+        if let Some(id) = &message.campaign_id {
+            self.uncount_campaign_claim(storage, id, &message.to)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_mint_failure_mode(&self, storage: &dyn Storage) -> StdResult<MintFailureMode> {
+        Ok(self
+            .mint_failure_mode
+            .may_load(storage)?
+            .unwrap_or(MintFailureMode::Revert))
+    }
+
+    // Resolves who receives a claim's fee and how much is charged. A zero signed fee falls back
+    // to the membership contract's default fee.
+    pub fn fee_payout(
+        &self,
+        storage: &dyn Storage,
+        verifying_contract: &Addr,
+        campaign_id: Option<&str>,
+        fee: &Coin,
+    ) -> StdResult<(Vec<FeeShare>, Coin)> {
+        let default_fee = self
+            .membership_contracts
+            .may_load(storage, verifying_contract)?
+            .and_then(|membership| membership.default_fee);
+
+        let fee = match default_fee {
+            Some(default_fee) if fee.amount.is_zero() => default_fee,
+            _ => fee.to_owned(),
+        };
+
+        let split = self.active_fee_split(storage, Some(verifying_contract), campaign_id)?;
+
+        Ok((split, fee))
+    }
+
+    // Referrer of a claim and its share of `amount`, the rest of the fee goes to the fee split.
+    pub fn referral_cut(
+        &self,
+        storage: &dyn Storage,
+        referrer: Option<&Addr>,
+        amount: Uint128,
+    ) -> StdResult<Option<(Addr, Uint128)>> {
+        let referrer = match referrer {
+            Some(referrer) => referrer,
+            None => return Ok(None),
+        };
+        let bps = self.referral_bps.may_load(storage)?.unwrap_or_default();
+        Ok(Some((
+            referrer.to_owned(),
+            amount.multiply_ratio(bps, FULL_BPS),
+        )))
+    }
+
+    // Counts a referred claim and adds the referrer's cut to its earnings
+    pub fn record_referral(
+        &self,
+        storage: &mut dyn Storage,
+        referrer: &Addr,
+        reward: Option<Coin>,
+        cw20_reward: Option<Cw20CoinVerified>,
+    ) -> StdResult<()> {
+        let mut stats = self
+            .referrals
+            .may_load(storage, referrer)?
+            .unwrap_or_default();
+        stats.count += 1;
+        if let Some(reward) = reward.filter(|reward| !reward.amount.is_zero()) {
+            match stats.earnings.iter_mut().find(|c| c.denom == reward.denom) {
+                Some(coin) => coin.amount += reward.amount,
+                None => stats.earnings.push(reward),
+            }
+        }
+        if let Some(reward) = cw20_reward.filter(|reward| !reward.amount.is_zero()) {
+            match stats
+                .cw20_earnings
+                .iter_mut()
+                .find(|c| c.address == reward.address)
+            {
+                Some(coin) => coin.amount += reward.amount,
+                None => stats.cw20_earnings.push(reward),
+            }
+        }
+        self.referrals.save(storage, referrer, &stats)
+    }
+
+    pub fn is_vault_mode(&self, storage: &dyn Storage) -> StdResult<bool> {
+        Ok(self.vault_mode.may_load(storage)?.unwrap_or_default())
+    }
+
+    // Adds a fee to the lifetime and membership contract totals, and to the vault balance when
+    // fees are kept in the contract.
+    pub fn record_revenue(
+        &self,
+        storage: &mut dyn Storage,
+        verifying_contract: &Addr,
+        fee: &Coin,
+    ) -> StdResult<()> {
+        if fee.amount.is_zero() {
+            return Ok(());
+        }
+        let add =
+            |total: Option<Uint128>| Ok::<_, StdError>(total.unwrap_or_default() + fee.amount);
+
+        self.fee_totals.update(storage, &fee.denom, add)?;
+        self.contract_revenue
+            .update(storage, (verifying_contract, &fee.denom), add)?;
+        if self.is_vault_mode(storage)? {
+            self.vault_balances.update(storage, &fee.denom, add)?;
+        }
+        Ok(())
+    }
+
+    // `record_revenue` for fees paid in a cw20 token, tracked by token contract
+    pub fn record_cw20_revenue(
+        &self,
+        storage: &mut dyn Storage,
+        verifying_contract: &Addr,
+        fee: &Cw20CoinVerified,
+    ) -> StdResult<()> {
+        if fee.amount.is_zero() {
+            return Ok(());
+        }
+        let add =
+            |total: Option<Uint128>| Ok::<_, StdError>(total.unwrap_or_default() + fee.amount);
+
+        self.cw20_fee_totals.update(storage, &fee.address, add)?;
+        self.cw20_contract_revenue
+            .update(storage, (verifying_contract, &fee.address), add)?;
+        if self.is_vault_mode(storage)? {
+            self.cw20_vault_balances
+                .update(storage, &fee.address, add)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_referrer(&self, message: &Message) -> Result<(), ContractError> {
+        if message.referrer.as_ref() == Some(&message.to) {
+            return Err(ContractError::SelfReferral {});
+        }
+        Ok(())
+    }
+
+    // Recipients of a claim's fee, from the most specific setting: the campaign treasury, the
+    // membership contract's split or treasury, the global split and finally the treasury.
+    pub fn active_fee_split(
+        &self,
+        storage: &dyn Storage,
+        verifying_contract: Option<&Addr>,
+        campaign_id: Option<&str>,
+    ) -> StdResult<Vec<FeeShare>> {
+        let whole = |address: Addr| {
+            vec![FeeShare {
+                address,
+                bps: FULL_BPS,
+            }]
+        };
+
+        if let Some(id) = campaign_id {
+            let campaign = self.campaigns.may_load(storage, id)?;
+            if let Some(treasury) = campaign.and_then(|campaign| campaign.config.treasury) {
+                return Ok(whole(treasury));
+            }
+        }
+
+        if let Some(contract) = verifying_contract {
+            if let Some(membership) = self.membership_contracts.may_load(storage, contract)? {
+                if let Some(split) = membership.fee_split {
+                    return Ok(split);
+                }
+                if let Some(treasury) = membership.treasury {
+                    return Ok(whole(treasury));
+                }
+            }
+        }
+
+        match self.fee_split.may_load(storage)? {
+            Some(split) => Ok(split),
+            None => Ok(whole(self.treasury.load(storage)?)),
+        }
+    }
+
+    pub fn check_membership_contract(
+        &self,
+        storage: &dyn Storage,
+        verifying_contract: &Addr,
+    ) -> Result<(), ContractError> {
+        match self
+            .membership_contracts
+            .may_load(storage, verifying_contract)?
+        {
+            Some(membership) if membership.enabled => Ok(()),
+            Some(_) => Err(ContractError::MembershipContractDisabled {
+                contract: verifying_contract.to_string(),
+            }),
+            None => Err(ContractError::MembershipContractNotRegistered {
+                contract: verifying_contract.to_string(),
+            }),
+        }
+    }
+
+    pub fn check_campaign(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        message: &Message,
+    ) -> Result<(), ContractError> {
+        match &message.campaign_id {
+            Some(id) => self.check_campaign_claim(
+                storage,
+                env,
+                id,
+                &message.verifying_contract,
+                &message.to,
+                &message.fee,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    // Checks a claim of `fee` by `recipient` for `verifying_contract` against campaign `id`
+    pub fn check_campaign_claim(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        id: &str,
+        verifying_contract: &Addr,
+        recipient: &Addr,
+        fee: &Coin,
+    ) -> Result<(), ContractError> {
+        let campaign = self
+            .campaigns
+            .may_load(storage, id)?
+            .ok_or_else(|| ContractError::CampaignNotFound { id: id.to_owned() })?;
+        let config = &campaign.config;
+
+        if campaign.closed {
+            return Err(ContractError::CampaignClosed { id: id.to_owned() });
+        }
+        if config.verifying_contract != verifying_contract {
+            return Err(ContractError::CampaignContractMismatch { id: id.to_owned() });
+        }
+
+        let now = env.block.time;
+        let not_started = matches!(config.start_time, Some(start) if start > now);
+        let ended = matches!(config.end_time, Some(end) if end <= now);
+        if not_started || ended {
+            return Err(ContractError::CampaignNotActive { id: id.to_owned() });
+        }
+
+        if matches!(config.max_claims, Some(max) if campaign.claimed >= max) {
+            return Err(ContractError::CampaignSupplyReached { id: id.to_owned() });
+        }
+        if let Some(max) = config.max_claims_per_address {
+            let claimed = self
+                .campaign_claims
+                .may_load(storage, (id, recipient))?
+                .unwrap_or_default();
+            if claimed >= max {
+                return Err(ContractError::CampaignAddressLimitReached { id: id.to_owned() });
+            }
+        }
+
+        if let Some(required) = &config.fee {
+            if required != fee {
+                return Err(ContractError::CampaignFeeMismatch {
+                    required: required.to_owned(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Contracts migrated from before digest versioning keep the legacy hashing until an admin
+    // sets `V1`.
+    pub fn get_digest_version(&self, storage: &dyn Storage) -> StdResult<DigestVersion> {
+        Ok(self
+            .digest_version
+            .may_load(storage)?
+            .unwrap_or(DigestVersion::Legacy))
+    }
+
+    pub fn check_claim_domain(&self, env: &Env, message: &Message) -> Result<(), ContractError> {
+        if message.chain_id != env.block.chain_id {
+            return Err(ContractError::WrongChainId {
+                expected: message.chain_id.to_owned(),
+                actual: env.block.chain_id.to_owned(),
+            });
+        }
+        if message.claim_contract != env.contract.address {
+            return Err(ContractError::WrongClaimContract {
+                expected: message.claim_contract.to_string(),
+                actual: env.contract.address.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_claim_window(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        message: &Message,
+    ) -> Result<(), ContractError> {
+        if message.expires_at <= message.issued_at {
+            return Err(ContractError::InvalidClaimWindow {});
+        }
+
+        if let Some(max_seconds) = self.max_claim_validity.may_load(storage)? {
+            let validity = message.expires_at.seconds() - message.issued_at.seconds();
+            if validity > max_seconds {
+                return Err(ContractError::ClaimValidityTooLong { max_seconds });
+            }
+        }
+
+        let now = env.block.time;
+        if message.issued_at > now.plus_seconds(CLAIM_CLOCK_SKEW) {
+            return Err(ContractError::ClaimNotYetValid {
+                issued_at: message.issued_at,
+            });
+        }
+        if message.expires_at <= now {
+            return Err(ContractError::ClaimExpired {
+                expires_at: message.expires_at,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Read-only checks a claim must pass before it is marked used, returning its issuers.
+    // `cw20_paid` is the token received with the claim and must match its `cw20_fee`.
+    pub fn check_claim(
+        &self,
+        deps: Deps,
+        env: &Env,
+        sender: &Addr,
+        claim: &Claim,
+        cw20_paid: Option<&Cw20CoinVerified>,
+    ) -> Result<Vec<Addr>, ContractError> {
+        if claim.message.cw20_fee.as_ref() != cw20_paid {
+            return Err(ContractError::Cw20FeeMismatch {});
+        }
+
+        let validation = self.validate_claim(deps, env, claim)?;
+
+        if validation.is_revoked {
+            return Err(ContractError::ClaimRevoked {});
+        }
+
+        if !validation.is_valid() {
+            return Err(ContractError::VerificationFailure {
+                has_role: validation.has_role,
+                is_duplicate: validation.is_duplicate,
+                is_sign_valid: validation.is_sign_valid,
+            });
+        }
+
+        self.check_recipient(deps, sender, claim)?;
+        self.check_referrer(&claim.message)?;
+
+        Ok(validation.signers)
+    }
+
+    // Fee split, charged fee and overpayment of a claim paid with the attached funds
+    pub fn check_claim_fee(
+        &self,
+        storage: &dyn Storage,
+        info: &MessageInfo,
+        message: &Message,
+    ) -> Result<(Vec<FeeShare>, Coin, Uint128), ContractError> {
+        let (split, fee) = self.fee_payout(
+            storage,
+            &message.verifying_contract,
+            message.campaign_id.as_deref(),
+            &message.fee,
+        )?;
+        let refund = check_fee_payment(info, &fee)?;
+        Ok((split, fee, refund))
+    }
+
     pub fn validate_claim(
         &self,
         deps: Deps,
-        message: Message,
-        signature: Binary,
-        recovery_byte: u8,
-    ) -> StdResult<(bool, bool, bool)> {
-        let addr = recover_signer(
+        env: &Env,
+        claim: &Claim,
+    ) -> Result<ClaimValidation, ContractError> {
+        let message = &claim.message;
+        self.check_claim_domain(env, message)?;
+        self.check_membership_contract(deps.storage, &message.verifying_contract)?;
+        self.check_claim_window(deps.storage, env, message)?;
+        self.check_campaign(deps.storage, env, message)?;
+        parse_extension(message.extension.as_ref())?;
+
+        let version = self.get_digest_version(deps.storage)?;
+        let hash = message_digest(message, version)?;
+        let (addr, is_verified) = self.claim_signer(deps, env, claim, &hash)?;
+
+        let has_role = self.is_claim_issuer(deps, &message.verifying_contract, &addr)?;
+
+        let is_sign_valid = is_verified && message.from == addr;
+
+        let signers = self.co_signers(deps, env, claim, &hash, addr)?;
+
+        let is_duplicate = self.is_claim_used(deps.storage, claim)?;
+        deps.api.debug(&format!("Is duplicate: {}", is_duplicate));
+
+        let is_revoked = self.is_claim_revoked(deps.storage, message)?;
+
+        Ok(ClaimValidation {
+            is_duplicate,
+            is_revoked,
+            is_sign_valid,
+            has_role,
+            signers,
+        })
+    }
+
+    // Returns the issuer behind the claim's primary signature and whether it checked out
+    pub fn claim_signer(
+        &self,
+        deps: Deps,
+        env: &Env,
+        claim: &Claim,
+        hash: &[u8],
+    ) -> Result<(Addr, bool), ContractError> {
+        self.resolve_signer(
             deps,
-            message.to_owned(),
-            signature.to_owned(),
-            recovery_byte,
-        )?;
+            env,
+            &claim.message,
+            &claim.issuer_signature(),
+            Some(&claim.message.from),
+            hash,
+        )
+    }
+
+    // Collects the distinct issuers behind a claim, starting with `signer`, and enforces the
+    // issuer threshold of its campaign or membership contract
+    fn co_signers(
+        &self,
+        deps: Deps,
+        env: &Env,
+        claim: &Claim,
+        hash: &[u8],
+        signer: Addr,
+    ) -> Result<Vec<Addr>, ContractError> {
+        let message = &claim.message;
+        let mut signers = vec![signer];
+
+        for co_signature in claim.co_signatures.iter().flatten() {
+            let (co_signer, is_verified) =
+                self.resolve_signer(deps, env, message, co_signature, None, hash)?;
+            if !is_verified
+                || !self.is_claim_issuer(deps, &message.verifying_contract, &co_signer)?
+            {
+                return Err(ContractError::InvalidCoSignature {
+                    signer: co_signer.to_string(),
+                });
+            }
+            if signers.contains(&co_signer) {
+                return Err(ContractError::DuplicateSigner {
+                    signer: co_signer.to_string(),
+                });
+            }
+            signers.push(co_signer);
+        }
+
+        let required = self.issuer_threshold(deps.storage, message)?;
+        if (signers.len() as u32) < required {
+            return Err(ContractError::IssuerThresholdNotMet {
+                required,
+                signers: signers.len() as u32,
+            });
+        }
 
+        Ok(signers)
+    }
+
+    // Campaign thresholds take precedence over the membership contract's, defaulting to one
+    pub fn issuer_threshold(&self, storage: &dyn Storage, message: &Message) -> StdResult<u32> {
+        if let Some(id) = &message.campaign_id {
+            if let Some(threshold) = self
+                .campaigns
+                .may_load(storage, id)?
+                .and_then(|campaign| campaign.config.issuer_threshold)
+            {
+                return Ok(threshold);
+            }
+        }
+        Ok(self
+            .membership_contracts
+            .may_load(storage, &message.verifying_contract)?
+            .and_then(|membership| membership.issuer_threshold)
+            .unwrap_or(1))
+    }
+
+    pub fn is_claim_issuer(
+        &self,
+        deps: Deps,
+        verifying_contract: &Addr,
+        address: &Addr,
+    ) -> Result<bool, ContractError> {
         let has_claim_issuer_role_msg: MemberhsipQuery = MemberhsipQuery::HasRole {
-            address: addr.to_owned(),
+            address: address.to_owned(),
             role: Role::ClaimIssuer,
         };
 
-        let query_response: HasRoleResponse =
-            deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-                contract_addr: message.verifying_contract.to_string(),
+        let query_response: HasRoleResponse = deps
+            .querier
+            .query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: verifying_contract.to_string(),
                 msg: to_json_binary(&has_claim_issuer_role_msg)?,
-            }))?;
+            }))
+            .map_err(|e| ContractError::ValidationError { msg: e.to_string() })?;
+
+        Ok(query_response.value)
+    }
 
-        let has_role = query_response.value;
+    // Claims go to `to` and can be submitted by another sender when the issuer marked them
+    // relayable or the recipient signed a consent
+    pub fn check_recipient(
+        &self,
+        deps: Deps,
+        sender: &Addr,
+        claim: &Claim,
+    ) -> Result<(), ContractError> {
+        let message = &claim.message;
+        if message.to == sender || message.relayable.unwrap_or_default() {
+            return Ok(());
+        }
 
-        let is_sign_valid = &message.from == addr;
+        let consent = claim
+            .consent
+            .as_ref()
+            .ok_or(ContractError::NotReceiver {})?;
 
-        let is_duplicate = self
-            .claim_map
-            .may_load(deps.storage, &signature)?
-            .unwrap_or_default();
-        deps.api.debug(&format!("Is duplicate: {}", is_duplicate));
+        let pubkey = normalize_pubkey(&consent.pubkey)
+            .map_err(|_| ContractError::InvalidRecipientConsent {})?;
+        if pubkey_to_address(&pubkey, &message.bech32_hre)? != message.to {
+            return Err(ContractError::InvalidRecipientConsent {});
+        }
+
+        let version = self.get_digest_version(deps.storage)?;
+        let digest = consent_digest(&message_digest(message, version)?);
+        let is_verified = if consent.adr036 {
+            verify_adr036_signature(
+                deps,
+                &message.to,
+                &digest,
+                &consent.signature,
+                &consent.pubkey,
+            )
+        } else {
+            deps.api
+                .secp256k1_verify(&digest, &consent.signature, &pubkey)
+                .map_err(StdError::from)
+        }
+        .unwrap_or_default();
 
-        Ok((is_duplicate, is_sign_valid, has_role))
+        if !is_verified {
+            return Err(ContractError::InvalidRecipientConsent {});
+        }
+        Ok(())
+    }
+
+    // Returns the address behind an issuer signature and whether the signature checked out.
+    // ADR-036 sign docs name `adr036_signer`, or the address the public key derives to.
+    fn resolve_signer(
+        &self,
+        deps: Deps,
+        env: &Env,
+        message: &Message,
+        issuer_signature: &IssuerSignature,
+        adr036_signer: Option<&Addr>,
+        hash: &[u8],
+    ) -> Result<(Addr, bool), ContractError> {
+        let invalid = |e: StdError| ContractError::ValidationError { msg: e.to_string() };
+        let IssuerSignature {
+            signature,
+            recovery_byte,
+            sign_mode,
+        } = issuer_signature;
+        let sign_mode = sign_mode.as_ref().unwrap_or(&SignMode::Secp256k1);
+
+        let is_ed25519 = matches!(sign_mode, SignMode::Ed25519 { .. });
+        if !is_ed25519 && !is_low_s_signature(signature) {
+            return Err(ContractError::MalleableSignature {});
+        }
+
+        match sign_mode {
+            SignMode::Secp256k1 => {
+                let pubkey =
+                    recover_pubkey(deps, hash, signature, *recovery_byte).map_err(invalid)?;
+                let addr = self.secp256k1_issuer(deps, env, &pubkey, message)?;
+                Ok((addr, true))
+            }
+            SignMode::Adr036 { pubkey } => {
+                let normalized = normalize_pubkey(pubkey).map_err(invalid)?;
+                let signer = match adr036_signer {
+                    Some(signer) => signer.to_owned(),
+                    None => pubkey_to_address(&normalized, &message.bech32_hre).map_err(invalid)?,
+                };
+                let is_verified = verify_adr036_signature(deps, &signer, hash, signature, pubkey)
+                    .map_err(invalid)?;
+                let addr = self.secp256k1_issuer(deps, env, &normalized, message)?;
+                Ok((addr, is_verified))
+            }
+            SignMode::Eip191 => {
+                let eth_address =
+                    recover_eth_address(deps, &eip191_hash(hash), signature, *recovery_byte)
+                        .map_err(invalid)?;
+                let issuer = self
+                    .registered_issuer(
+                        deps.storage,
+                        env,
+                        KeyType::Eth,
+                        &eth_address,
+                        &message.verifying_contract,
+                    )?
+                    .ok_or(ContractError::IssuerKeyNotRegistered {})?;
+                Ok((issuer, true))
+            }
+            SignMode::Ed25519 { pubkey } => {
+                let issuer = self
+                    .registered_issuer(
+                        deps.storage,
+                        env,
+                        KeyType::Ed25519,
+                        pubkey,
+                        &message.verifying_contract,
+                    )?
+                    .ok_or(ContractError::IssuerKeyNotRegistered {})?;
+                let is_verified = deps
+                    .api
+                    .ed25519_verify(hash, signature, pubkey)
+                    .map_err(|e| invalid(e.into()))?;
+                Ok((issuer, is_verified))
+            }
+        }
+    }
+
+    // Registered secp256k1 keys sign for their issuer, others for the address they derive to
+    fn secp256k1_issuer(
+        &self,
+        deps: Deps,
+        env: &Env,
+        pubkey: &[u8],
+        message: &Message,
+    ) -> Result<Addr, ContractError> {
+        let registered = self.registered_issuer(
+            deps.storage,
+            env,
+            KeyType::Secp256k1,
+            pubkey,
+            &message.verifying_contract,
+        )?;
+        match registered {
+            Some(issuer) => Ok(issuer),
+            None => pubkey_to_address(pubkey, &message.bech32_hre)
+                .map_err(|e| ContractError::ValidationError { msg: e.to_string() }),
+        }
     }
 }